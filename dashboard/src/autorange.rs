@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use crate::hardware::{Channel, SensorId};
+use crate::ltr303::{Gain, RawChannels, SensorConfig};
+use crate::sensors::SensorSettings;
+
+/// Counts at which a channel clips.
+pub const SATURATED: u16 = 0xFFFF;
+/// CH0 counts below which the gain is raised.
+pub const LOW_COUNTS: u16 = 256;
+
+/// What to do with a raw frame checked by [`AutoRanger::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// The frame can be recorded.
+    InRange,
+    /// A channel clipped, the frame reads lower than the light reaching the sensor.
+    Saturated,
+    /// The frame was measured with the previous gain of a switching sensor and is dropped.
+    Settling,
+}
+
+/// Outcome of [`AutoRanger::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Check {
+    pub status: FrameStatus,
+    /// Gain the frame was measured with.
+    pub gain: Gain,
+    /// New settings to send to the sensor.
+    pub change: Option<SensorConfig>,
+}
+
+/// Range of a single sensor.
+#[derive(Debug, Clone, Copy)]
+struct SensorRange {
+    /// Gain the sensor was last set to.
+    gain: Gain,
+    /// Time by which the sensor should have switched to `gain`, after which it is set again.
+    deadline: Option<Instant>,
+}
+
+/// Steps the gain of every sensor down when it saturates and up when its counts are close to
+/// zero.
+#[derive(Debug, Default)]
+pub struct AutoRanger {
+    sensors: BTreeMap<SensorId, SensorRange>,
+}
+
+impl AutoRanger {
+    /// Returns every sensor to its configured gain.
+    pub fn reset(&mut self) {
+        self.sensors.clear();
+    }
+
+    /// Returns the settings the sensor of `channel` is set to.
+    pub fn config_for(&self, channel: &Channel, settings: &SensorSettings) -> SensorConfig {
+        let mut config = settings.config_for(&channel.label);
+        if let Some(range) = self.sensors.get(&channel.id()) {
+            config.gain = range.gain;
+        }
+        config
+    }
+
+    /// Checks a raw frame of the sensor of `channel` measured with `gain` and received at `now`.
+    pub fn check(
+        &mut self,
+        channel: &Channel,
+        settings: &SensorSettings,
+        channels: RawChannels,
+        gain: Option<Gain>,
+        now: Instant,
+    ) -> Check {
+        let config = self.config_for(channel, settings);
+        let saturated = channels.ch0 == SATURATED || channels.ch1 == SATURATED;
+        let status = if saturated { FrameStatus::Saturated } else { FrameStatus::InRange };
+        let mut check = Check { status, gain: gain.unwrap_or(config.gain), change: None };
+
+        if !settings.auto_range {
+            return check;
+        }
+
+        let range = self
+            .sensors
+            .entry(channel.id())
+            .or_insert(SensorRange { gain: config.gain, deadline: Some(now + settle_time(&config)) });
+        if gain != Some(range.gain) {
+            check.status = FrameStatus::Settling;
+            if range.deadline.is_some_and(|deadline| now >= deadline) {
+                range.deadline = Some(now + settle_time(&config));
+                check.change = Some(config);
+            }
+            return check;
+        }
+        range.deadline = None;
+
+        let step = if saturated {
+            config.gain.lower()
+        } else if channels.ch0 < LOW_COUNTS {
+            config.gain.higher()
+        } else {
+            None
+        };
+        if let Some(gain) = step {
+            let config = SensorConfig { gain, ..config };
+            range.gain = gain;
+            range.deadline = Some(now + settle_time(&config));
+            check.change = Some(config);
+        }
+
+        check
+    }
+}
+
+/// Returns how long a sensor takes to deliver frames measured with `config` once set to it.
+fn settle_time(config: &SensorConfig) -> Duration {
+    let cycle = config.integration_time.millis().max(config.measurement_rate.millis());
+    Duration::from_millis(2 * cycle as u64 + 200)
+}
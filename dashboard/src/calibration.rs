@@ -0,0 +1,470 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use dirs::config_dir;
+use eframe::egui;
+use eframe::egui::{Context, Ui};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use serialport::{SerialPortInfo, SerialPortType};
+use crate::hardware::{HardwareMapping, SensorId};
+use crate::sampling::Aggregate;
+
+/// Version of the calibration file layout written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum CalibrationError {
+    /// No reference condition was captured.
+    NoCaptures,
+    /// The sensor read the same under every reference condition, or nothing under the only one.
+    Degenerate(SensorId),
+    /// The file could not be read, written or removed.
+    Io { path: PathBuf, source: io::Error },
+    /// The file does not contain a valid calibration.
+    Parse { path: PathBuf, source: serde_json::Error },
+    /// The calibration could not be serialized.
+    Serialize(serde_json::Error),
+    /// The calibration has a newer format version than this build reads.
+    UnsupportedVersion(u32),
+    /// The configuration directory of the user could not be determined.
+    NoConfigDirectory,
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CalibrationError::NoCaptures => write!(f, "Capture at least one reference condition first."),
+            CalibrationError::Degenerate(sensor) => write!(
+                f,
+                "The {} cannot be calibrated, capture conditions it reads differently.",
+                sensor
+            ),
+            CalibrationError::Io { path, source } => write!(f, "Unable to access {}: {}", path.display(), source),
+            CalibrationError::Parse { path, source } => write!(f, "{} is not a valid calibration: {}", path.display(), source),
+            CalibrationError::Serialize(source) => write!(f, "Unable to serialize the calibration: {}", source),
+            CalibrationError::UnsupportedVersion(version) => write!(
+                f,
+                "Calibration format version {} is not supported, this dashboard reads up to version {}.",
+                version, FORMAT_VERSION
+            ),
+            CalibrationError::NoConfigDirectory => write!(f, "Unable to find the configuration folder."),
+        }
+    }
+}
+
+impl Error for CalibrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CalibrationError::Io { source, .. } => Some(source),
+            CalibrationError::Parse { source, .. } => Some(source),
+            CalibrationError::Serialize(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Readings of every sensor under one reference condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    /// Illuminance the sensors should read, in lux.
+    pub reference: f64,
+    /// Mean uncalibrated reading of each sensor.
+    pub readings: BTreeMap<SensorId, f64>,
+}
+
+impl Capture {
+    /// Creates a capture of `readings` whose reference is the mean of every sensor.
+    pub fn new(readings: BTreeMap<SensorId, f64>) -> Self {
+        let reference = readings.values().sum::<f64>() / readings.len().max(1) as f64;
+        Self { reference, readings }
+    }
+}
+
+/// Linear correction of a single sensor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorCalibration {
+    #[serde(flatten)]
+    pub sensor: SensorId,
+    pub gain: f64,
+    pub offset: f64,
+    /// Coefficient of determination of the fit, when fitted to more than one condition.
+    #[serde(default)]
+    pub r_squared: Option<f64>,
+    /// Uncalibrated reading and reference of every condition the sensor was fitted to.
+    #[serde(default)]
+    pub points: Vec<[f64; 2]>,
+}
+
+impl SensorCalibration {
+    /// Returns the calibrated value of an uncalibrated `reading`.
+    pub fn apply(&self, reading: f64) -> f64 {
+        self.gain * reading + self.offset
+    }
+}
+
+/// Corrections of the sensors of one board, stored in a file named after the board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub format_version: u32,
+    /// Board the sensors are soldered on, see [`device_key`].
+    pub device: String,
+    pub created: DateTime<Local>,
+    /// Correction of each calibrated sensor, ordered by sensor.
+    pub sensors: Vec<SensorCalibration>,
+}
+
+impl Calibration {
+    /// Fits the gain and offset of every sensor of `device` to the references of `captures`.
+    pub fn fit(device: &str, captures: &[Capture]) -> Result<Calibration, CalibrationError> {
+        if captures.is_empty() {
+            return Err(CalibrationError::NoCaptures);
+        }
+
+        let mut ids: Vec<SensorId> = captures.iter().flat_map(|capture| capture.readings.keys().copied()).collect();
+        ids.sort();
+        ids.dedup();
+
+        let mut sensors = vec![];
+        for sensor in ids {
+            let points: Vec<[f64; 2]> = captures
+                .iter()
+                .filter_map(|capture| capture.readings.get(&sensor).map(|reading| [*reading, capture.reference]))
+                .collect();
+            sensors.push(fit_sensor(sensor, points).ok_or(CalibrationError::Degenerate(sensor))?);
+        }
+
+        Ok(Calibration {
+            format_version: FORMAT_VERSION,
+            device: device.to_string(),
+            created: Local::now(),
+            sensors,
+        })
+    }
+
+    /// Returns the correction of `sensor`, if it was calibrated.
+    pub fn sensor(&self, sensor: SensorId) -> Option<&SensorCalibration> {
+        self.sensors.iter().find(|calibration| calibration.sensor == sensor)
+    }
+
+    /// Returns the calibrated value of an uncalibrated `reading` of `sensor`.
+    pub fn apply(&self, sensor: SensorId, reading: f64) -> f64 {
+        self.sensor(sensor)
+            .map(|calibration| calibration.apply(reading))
+            .unwrap_or(reading)
+    }
+
+    /// Returns where the calibration of `device` is stored.
+    pub fn path_for(device: &str) -> Result<PathBuf, CalibrationError> {
+        let mut path = config_dir().ok_or(CalibrationError::NoConfigDirectory)?;
+        path.push("tinywell");
+        path.push("calibration");
+        path.push(format!("{}.json", device));
+        Ok(path)
+    }
+
+    /// Loads the calibration of `device`, if it was ever calibrated.
+    pub fn load_for(device: &str) -> Result<Option<Calibration>, CalibrationError> {
+        match Calibration::load(&Calibration::path_for(device)?) {
+            Err(CalibrationError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => Ok(None),
+            result => result.map(Some),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Calibration, CalibrationError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|source| CalibrationError::Io { path: path.to_path_buf(), source })?;
+        let value: Value = serde_json::from_str(&contents)
+            .map_err(|source| CalibrationError::Parse { path: path.to_path_buf(), source })?;
+
+        let version = value.get("format_version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        if version > FORMAT_VERSION {
+            return Err(CalibrationError::UnsupportedVersion(version));
+        }
+
+        serde_json::from_value(value).map_err(|source| CalibrationError::Parse { path: path.to_path_buf(), source })
+    }
+
+    /// Removes the calibration of `device`, leaving its sensors uncalibrated.
+    pub fn remove_for(device: &str) -> Result<(), CalibrationError> {
+        let path = Calibration::path_for(device)?;
+        match fs::remove_file(&path) {
+            Err(source) if source.kind() != io::ErrorKind::NotFound => Err(CalibrationError::Io { path, source }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the calibration to `path`, creating its folder if needed.
+    pub fn save(&self, path: &Path) -> Result<(), CalibrationError> {
+        let io_error = |source| CalibrationError::Io { path: path.to_path_buf(), source };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        let json_data = serde_json::to_string_pretty(self).map_err(CalibrationError::Serialize)?;
+        fs::write(path, json_data).map_err(io_error)
+    }
+}
+
+/// Fits `points` of `sensor`, pairs of uncalibrated reading and reference, with a line.
+fn fit_sensor(sensor: SensorId, points: Vec<[f64; 2]>) -> Option<SensorCalibration> {
+    let n = points.len() as f64;
+    if points.len() == 1 {
+        let [reading, reference] = points[0];
+        return (reading != 0.0)
+            .then(|| SensorCalibration { sensor, gain: reference / reading, offset: 0.0, r_squared: None, points });
+    }
+
+    let mean_x = points.iter().map(|[x, _]| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|[_, y]| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|[x, _]| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|[x, y]| (x - mean_x) * (y - mean_y)).sum();
+    let syy: f64 = points.iter().map(|[_, y]| (y - mean_y).powi(2)).sum();
+    if sxx <= 0.0 {
+        return None;
+    }
+
+    let gain = sxy / sxx;
+    let r_squared = (syy > 0.0).then(|| sxy * sxy / (sxx * syy));
+    Some(SensorCalibration { sensor, gain, offset: mean_y - gain * mean_x, r_squared, points })
+}
+
+/// Returns the name the calibration of the board behind `port` is stored under.
+pub fn device_key(port: &SerialPortInfo) -> String {
+    let key = match &port.port_type {
+        SerialPortType::UsbPort(info) => match &info.serial_number {
+            Some(serial_number) => format!("{:04x}-{:04x}-{}", info.vid, info.pid, serial_number),
+            None => format!("{:04x}-{:04x}", info.vid, info.pid),
+        },
+        _ => port.port_name.clone(),
+    };
+
+    key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// What the user asked the [`CalibrationWindow`] to do with the calibration of the board.
+pub enum CalibrationAction {
+    Save(Calibration),
+    Remove,
+}
+
+/// Window capturing the sensors under reference conditions and fitting a [`Calibration`].
+pub struct CalibrationWindow {
+    pub open: bool,
+    /// How long the sensors are averaged for under each condition.
+    duration: Duration,
+    captures: Vec<Capture>,
+    capturing: Option<(Instant, BTreeMap<SensorId, Aggregate>)>,
+    fitted: Option<Calibration>,
+    error: Option<String>,
+}
+
+impl Default for CalibrationWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            duration: Duration::from_secs(10),
+            captures: vec![],
+            capturing: None,
+            fitted: None,
+            error: None,
+        }
+    }
+}
+
+impl CalibrationWindow {
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    /// Records an uncalibrated reading of `sensor` while capturing.
+    pub fn record(&mut self, sensor: SensorId, reading: f32) {
+        if let Some((_, readings)) = &mut self.capturing {
+            readings.entry(sensor).or_default().push(reading);
+        }
+    }
+
+    /// Ends the capture once the sensors were averaged for long enough.
+    pub fn update(&mut self) {
+        match &self.capturing {
+            Some((start, _)) if start.elapsed() >= self.duration => {}
+            _ => return,
+        }
+        let Some((_, readings)) = self.capturing.take() else {
+            return;
+        };
+        let readings: BTreeMap<SensorId, f64> = readings
+            .into_iter()
+            .filter_map(|(sensor, aggregate)| aggregate.mean().map(|mean| (sensor, mean as f64)))
+            .collect();
+        if readings.is_empty() {
+            self.error = Some("No reading was received, check the port of the board.".to_string());
+        } else {
+            self.captures.push(Capture::new(readings));
+            self.fitted = None;
+        }
+    }
+
+    /// Shows the window for the board `device`, calibrated with `active`.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        device: Option<&str>,
+        active: Option<&Calibration>,
+        mapping: &HardwareMapping,
+        enabled: bool,
+    ) -> Option<CalibrationAction> {
+        let mut action = None;
+        let mut open = self.open;
+
+        egui::Window::new("Sensor Calibration")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(enabled && device.is_some(), |ui| {
+                    action = self.show_contents(ui, device.unwrap_or_default(), active, mapping);
+                });
+            });
+
+        if self.is_capturing() {
+            ctx.request_repaint();
+        }
+        self.open = open;
+        action
+    }
+
+    fn show_contents(
+        &mut self,
+        ui: &mut Ui,
+        device: &str,
+        active: Option<&Calibration>,
+        mapping: &HardwareMapping,
+    ) -> Option<CalibrationAction> {
+        let mut action = None;
+
+        ui.label(format!("Board: {}", if device.is_empty() { "None" } else { device }));
+        match active {
+            Some(calibration) => ui.label(format!(
+                "Calibrated on {} for {} sensors.",
+                calibration.created.format("%Y-%m-%d %H:%M"),
+                calibration.sensors.len()
+            )),
+            None => ui.label("Not calibrated."),
+        };
+        ui.separator();
+
+        ui.label("Expose every sensor to the same light, capture it, and repeat under other conditions.");
+        ui.horizontal(|ui| {
+            let mut seconds = self.duration.as_secs_f64();
+            if ui.add(
+                egui::DragValue::new(&mut seconds)
+                    .speed(1.0)
+                    .clamp_range(1.0..=600.0)
+                    .prefix("Average for ")
+                    .suffix(" s")
+            ).changed() {
+                self.duration = Duration::from_secs_f64(seconds);
+            }
+
+            match &self.capturing {
+                Some((start, _)) => {
+                    let left = self.duration.saturating_sub(start.elapsed());
+                    ui.spinner();
+                    ui.label(format!("Capturing, {:.0} s left", left.as_secs_f64().ceil()));
+                }
+                None => {
+                    if ui.button("Capture Condition").clicked() {
+                        self.capturing = Some((Instant::now(), BTreeMap::new()));
+                        self.error = None;
+                    }
+                }
+            }
+        });
+
+        let mut removed = None;
+        egui::Grid::new("calibration_captures")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Condition");
+                ui.label("Reference [lux]")
+                    .on_hover_text("Defaults to the mean of every sensor, which makes the sensors agree with each other. Enter the reading of a lux meter to calibrate them in absolute terms.");
+                ui.label("");
+                ui.end_row();
+
+                for (idx, capture) in self.captures.iter_mut().enumerate() {
+                    ui.label(format!("{} ({} sensors)", idx + 1, capture.readings.len()));
+                    if ui.add(egui::DragValue::new(&mut capture.reference).speed(0.1)).changed() {
+                        self.fitted = None;
+                    }
+                    if ui.small_button("✕").clicked() {
+                        removed = Some(idx);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(idx) = removed {
+            self.captures.remove(idx);
+            self.fitted = None;
+        }
+
+        if ui.add_enabled(!self.captures.is_empty(), egui::Button::new("Fit")).clicked() {
+            match Calibration::fit(device, &self.captures) {
+                Ok(calibration) => {
+                    self.fitted = Some(calibration);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if let Some(calibration) = &self.fitted {
+            ui.separator();
+            egui::Grid::new("calibration_fit")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    for column in ["Well", "Gain", "Offset [lux]", "R²"] {
+                        ui.label(column);
+                    }
+                    ui.end_row();
+
+                    for sensor in &calibration.sensors {
+                        match mapping.channel(sensor.sensor) {
+                            Some(channel) => ui.label(&channel.label),
+                            None => ui.label(sensor.sensor.to_string()),
+                        };
+                        ui.label(format!("{:.4}", sensor.gain));
+                        ui.label(format!("{:.3}", sensor.offset));
+                        ui.label(sensor.r_squared.map(|r| format!("{:.4}", r)).unwrap_or_default());
+                        ui.end_row();
+                    }
+                });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(self.fitted.is_some(), egui::Button::new("Save Calibration")).clicked() {
+                action = self.fitted.take().map(CalibrationAction::Save);
+                self.captures.clear();
+            }
+            if ui.add_enabled(active.is_some(), egui::Button::new("Remove Calibration")).clicked() {
+                action = Some(CalibrationAction::Remove);
+            }
+        });
+
+        action
+    }
+}
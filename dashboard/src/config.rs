@@ -1,68 +1,157 @@
-use std::{env, fs};
+use std::{env, fmt, fs, io};
+use std::error::Error;
+use std::fmt::Formatter;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use dirs::config_dir;
-use crate::microplate::MicroPlate;
+use crate::sequence::{migrate, MigrationError, SequenceFile};
+use crate::sequence_toml::{from_toml, to_toml, TextFormatError};
 
-pub fn get_results_directory() -> PathBuf {
+/// Errors raised while reading or writing the configuration and sequence files.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be opened, read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// The file does not contain valid JSON for the expected type.
+    Parse { path: PathBuf, source: serde_json::Error },
+    /// The data could not be serialized.
+    Serialize(serde_json::Error),
+    /// The saved results directory no longer exists.
+    MissingDirectory(PathBuf),
+    /// The TOML sequence file could not be read or written.
+    TextFormat { path: PathBuf, source: TextFormatError },
+    /// The sequence file could not be upgraded to the current format.
+    Migration { path: PathBuf, source: MigrationError },
+    /// The sequence file does not contain any step.
+    EmptySequence(PathBuf),
+    /// The directory of the executable could not be determined.
+    NoExecutableDirectory,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "Unable to access {}: {}", path.display(), source),
+            ConfigError::Parse { path, source } => write!(f, "{} is not a valid file: {}", path.display(), source),
+            ConfigError::Serialize(source) => write!(f, "Unable to serialize the data: {}", source),
+            ConfigError::TextFormat { path, source } => write!(f, "Unable to process {}: {}", path.display(), source),
+            ConfigError::Migration { path, source } => write!(f, "Unable to load {}: {}", path.display(), source),
+            ConfigError::MissingDirectory(path) => write!(f, "The directory {} no longer exists.", path.display()),
+            ConfigError::EmptySequence(path) => write!(f, "{} does not contain any well pattern.", path.display()),
+            ConfigError::NoExecutableDirectory => write!(f, "Unable to find the folder of the executable."),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::Serialize(source) => Some(source),
+            ConfigError::TextFormat { source, .. } => Some(source),
+            ConfigError::Migration { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the results directory saved in the configuration, or the default one when none was
+/// saved yet.
+pub fn get_results_directory() -> Result<PathBuf, ConfigError> {
     if let Some(path) = get_config_path() {
-        if path.exists(){
-            let mut file = File::open(path).unwrap();
+        if path.exists() {
+            let contents = read_file(&path)?;
+            let save_directory: PathBuf = serde_json::from_str(&contents)
+                .map_err(|source| ConfigError::Parse { path, source })?;
 
-            let mut contents: String = Default::default();
-            file.read_to_string(&mut contents).unwrap();
-            let save_directory = serde_json::from_str(&contents).unwrap();
-            return save_directory
+            if !save_directory.is_dir() {
+                return Err(ConfigError::MissingDirectory(save_directory));
+            }
+            return Ok(save_directory);
         }
     }
 
-    PathBuf::from(
-        env::current_exe()
-            .expect("Failed to get current executable path")
-            .parent()
-            .expect("Unable to find parent folder"),
-    )
+    default_results_directory()
+}
+
+/// Returns the folder of the executable, where results are written unless configured otherwise.
+pub fn default_results_directory() -> Result<PathBuf, ConfigError> {
+    env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(PathBuf::from))
+        .ok_or(ConfigError::NoExecutableDirectory)
 }
 
-pub fn set_results_directory(save_directory: PathBuf) {
+pub fn set_results_directory(save_directory: PathBuf) -> Result<(), ConfigError> {
     if let Some(path) = get_config_path() {
         // Make sure the directory exists
         if let Some(parent) = path.parent() {
             if !parent.exists() {
-                fs::create_dir_all(parent).unwrap();
+                fs::create_dir_all(parent)
+                    .map_err(|source| ConfigError::Io { path: parent.to_path_buf(), source })?;
             }
         }
 
         // Save the projects to the JSON file
-        let json_data = serde_json::to_string_pretty(&save_directory).unwrap();
-        let mut file = File::create(path).unwrap();
-        file.write_all(json_data.as_bytes()).unwrap();
+        let json_data = serde_json::to_string_pretty(&save_directory).map_err(ConfigError::Serialize)?;
+        write_file(&path, json_data.as_bytes())?;
     }
+
+    Ok(())
 }
 
-pub fn save_sequence_as(file_path: PathBuf, sequence: Vec<MicroPlate>) {
-    let json_data = serde_json::to_string_pretty(&sequence)
-        .expect("Was unable to serialize the sequence provided");
-    let mut file = File::create(file_path.clone())
-        .unwrap_or_else(|_| panic!("Was unable to write to file {:?}", file_path.clone()));
-    file.write_all(json_data.as_bytes())
-        .unwrap_or_else(|_| panic!("Was unable to write serialized sequence to file {:?}", file_path.clone()));
+/// Saves `sequence` to `file_path`, in the compact TOML format when the file has a `.toml`
+/// extension and as JSON otherwise.
+pub fn save_sequence_as(file_path: PathBuf, sequence: &SequenceFile) -> Result<(), ConfigError> {
+    let data = if is_toml(&file_path) {
+        to_toml(sequence).map_err(|source| ConfigError::TextFormat { path: file_path.clone(), source })?
+    } else {
+        serde_json::to_string_pretty(sequence).map_err(ConfigError::Serialize)?
+    };
+    write_file(&file_path, data.as_bytes())
 }
 
-pub fn load_sequence(file_path: PathBuf) -> Vec<MicroPlate> {
-    if file_path.exists() {
-        let mut file = File::open(file_path.clone())
-            .unwrap_or_else(|_| panic!("Was unable to open file {:?}", file_path.clone()));
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)
-            .unwrap_or_else(|_| panic!("Was unable to read from file {:?}", file_path.clone()));
-        let sequence: Vec<MicroPlate> = serde_json::from_str(&contents)
-            .unwrap_or_else(|_| panic!("Was unable to serialize contents of file {:?}", file_path.clone()));
-        return sequence;
+/// Loads the sequence file at `file_path`, upgrading it from older format versions if needed.
+pub fn load_sequence(file_path: PathBuf) -> Result<SequenceFile, ConfigError> {
+    let contents = read_file(&file_path)?;
+    let sequence = if is_toml(&file_path) {
+        from_toml(&contents)
+            .map_err(|source| ConfigError::TextFormat { path: file_path.clone(), source })?
+    } else {
+        let value = serde_json::from_str(&contents)
+            .map_err(|source| ConfigError::Parse { path: file_path.clone(), source })?;
+        migrate(value)
+            .map_err(|source| ConfigError::Migration { path: file_path.clone(), source })?
+    };
+
+    if sequence.steps.is_empty() {
+        return Err(ConfigError::EmptySequence(file_path));
     }
 
-    vec![]
+    Ok(sequence)
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false)
+}
+
+fn read_file(path: &Path) -> Result<String, ConfigError> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+
+    Ok(contents)
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), ConfigError> {
+    File::create(path)
+        .and_then(|mut file| file.write_all(data))
+        .map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })
 }
 
 fn get_config_path() -> Option<PathBuf> {
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+use crate::microplate::MicroPlate;
+use crate::sample::{SampleRole, Samples};
+use crate::timeseries::{by_time, StepSpan, Timeline};
+use crate::wavelength::Wavelength;
+
+/// Quantity of the wells plotted or analysed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    /// The reading of the sensor, background-corrected when dark readings are taken.
+    #[default]
+    Reading,
+    /// Fraction of the light reaching the blanks that reaches the well.
+    Transmittance,
+    /// Apparent optical density, `−log10` of the transmittance.
+    OpticalDensity,
+}
+
+impl Quantity {
+    pub const ALL: [Quantity; 3] = [Quantity::Reading, Quantity::Transmittance, Quantity::OpticalDensity];
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Quantity::Reading => write!(f, "Reading"),
+            Quantity::Transmittance => write!(f, "Transmittance"),
+            Quantity::OpticalDensity => write!(f, "OD"),
+        }
+    }
+}
+
+/// Returns whether any well of `samples` is a blank.
+pub fn has_blanks(samples: &Samples) -> bool {
+    samples.values().any(|sample| sample.role == SampleRole::Blank)
+}
+
+/// Returns the apparent optical density of a transmittance.
+pub fn optical_density(transmittance: f64) -> f64 {
+    -transmittance.log10()
+}
+
+/// Mean reading of the lit blank wells of a step, for each wavelength.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Blanks {
+    levels: Vec<(Wavelength, f64)>,
+}
+
+impl Blanks {
+    /// Averages the readings of the lit blank wells of `plate` by wavelength.
+    pub fn new(plate: &MicroPlate, samples: &Samples, readings: &[(usize, f64)]) -> Blanks {
+        let mut sums: Vec<(Wavelength, f64, usize)> = vec![];
+        for (idx, reading) in readings {
+            let well = &plate.wells[*idx];
+            let blank = samples.get(&well.label).map(|sample| sample.role == SampleRole::Blank);
+            if !well.led_on || blank != Some(true) {
+                continue;
+            }
+
+            match sums.iter_mut().find(|(wavelength, _, _)| *wavelength == well.wavelength) {
+                Some((_, sum, count)) => {
+                    *sum += reading;
+                    *count += 1;
+                }
+                None => sums.push((well.wavelength.clone(), *reading, 1)),
+            }
+        }
+
+        Blanks {
+            levels: sums
+                .into_iter()
+                .map(|(wavelength, sum, count)| (wavelength, sum / count as f64))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Returns the mean reading of the blanks lit with `wavelength`.
+    pub fn level(&self, wavelength: &Wavelength) -> Option<f64> {
+        self.levels
+            .iter()
+            .find(|(blank, _)| blank == wavelength)
+            .map(|(_, level)| *level)
+    }
+
+    /// Returns the transmittance of the well at `idx` of `plate` given its `reading`.
+    pub fn transmittance(&self, plate: &MicroPlate, idx: usize, reading: f64) -> Option<f64> {
+        let well = &plate.wells[idx];
+        let level = self.level(&well.wavelength).filter(|_| well.led_on)?;
+        (level > 0.0 && reading > 0.0).then(|| reading / level)
+    }
+}
+
+/// Readings, transmittance and optical density of the wells of a run.
+#[derive(Debug, Clone, Default)]
+pub struct Quantities {
+    pub readings: Timeline,
+    pub transmittance: Timeline,
+    pub optical_density: Timeline,
+}
+
+impl Quantities {
+    /// Derives the other quantities from the `readings` of a recorded run.
+    pub fn from_readings(readings: Timeline, samples: &Samples, plate_for: impl Fn(&StepSpan) -> MicroPlate) -> Self {
+        let mut quantities = Quantities {
+            transmittance: Timeline { steps: readings.steps.clone(), ..Timeline::default() },
+            optical_density: Timeline { steps: readings.steps.clone(), ..Timeline::default() },
+            ..Quantities::default()
+        };
+
+        let rows = by_time(readings.series.iter().map(|(label, series)| (label, series.points())));
+        let mut plates: BTreeMap<usize, MicroPlate> = BTreeMap::new();
+
+        for (time, values) in rows {
+            let Some(span) = readings.step_at(time) else {
+                continue;
+            };
+            let plate = plates.entry(span.step).or_insert_with(|| plate_for(span));
+            let row: Vec<(usize, f64)> = values
+                .iter()
+                .filter_map(|(label, value)| {
+                    let idx = plate.wells.iter().position(|well| well.label == **label)?;
+                    Some((idx, *value))
+                })
+                .collect();
+
+            let blanks = Blanks::new(plate, samples, &row);
+            quantities.record_derived(time, plate, &blanks, &row);
+        }
+
+        quantities.readings = readings;
+        quantities
+    }
+
+    /// Returns the timeline of `quantity`.
+    pub fn get(&self, quantity: Quantity) -> &Timeline {
+        match quantity {
+            Quantity::Reading => &self.readings,
+            Quantity::Transmittance => &self.transmittance,
+            Quantity::OpticalDensity => &self.optical_density,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.readings.clear();
+        self.transmittance.clear();
+        self.optical_density.clear();
+    }
+
+    /// Starts the step `idx` in every timeline.
+    pub fn start_step(&mut self, time: f64, idx: usize, plate: &MicroPlate) {
+        self.readings.start_step(time, idx, plate);
+        self.transmittance.start_step(time, idx, plate);
+        self.optical_density.start_step(time, idx, plate);
+    }
+
+    /// Ends the running step in every timeline.
+    pub fn finish(&mut self, time: f64) {
+        self.readings.finish(time);
+        self.transmittance.finish(time);
+        self.optical_density.finish(time);
+    }
+
+    /// Records the `readings` of the wells of `plate` taken at `time`.
+    pub fn record(&mut self, time: f64, plate: &MicroPlate, blanks: &Blanks, readings: &[(usize, f64)]) {
+        for (idx, reading) in readings {
+            self.readings.record(&plate.wells[*idx].label, time, *reading);
+        }
+        self.record_derived(time, plate, blanks, readings);
+    }
+
+    fn record_derived(&mut self, time: f64, plate: &MicroPlate, blanks: &Blanks, readings: &[(usize, f64)]) {
+        for (idx, reading) in readings {
+            if let Some(transmittance) = blanks.transmittance(plate, *idx, *reading) {
+                let label = &plate.wells[*idx].label;
+                self.transmittance.record(label, time, transmittance);
+                self.optical_density.record(label, time, optical_density(transmittance));
+            }
+        }
+    }
+}
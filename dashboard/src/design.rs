@@ -0,0 +1,405 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::time::{SystemTime, UNIX_EPOCH};
+use eframe::egui;
+use eframe::egui::{Context, Ui};
+use serde::{Serialize, Deserialize};
+use crate::geometry::PlateGeometry;
+use crate::microplate::MicroPlate;
+use crate::sample::{SampleInfo, SampleRole, Samples};
+use crate::wavelength::Wavelength;
+
+/// How treatments are distributed over the usable wells of a plate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Layout {
+    /// Every treatment replicate is placed on a randomly chosen well.
+    #[default]
+    Randomised,
+    /// Wells are split into consecutive blocks holding one replicate of each treatment,
+    /// randomised within the block.
+    Blocked,
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Randomised => write!(f, "Randomised"),
+            Layout::Blocked => write!(f, "Blocked"),
+        }
+    }
+}
+
+/// What a single well receives during a step of a generated design.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Treatment {
+    Light(Wavelength),
+    Dark,
+}
+
+impl fmt::Display for Treatment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Treatment::Light(wavelength) => write!(f, "{}", wavelength),
+            Treatment::Dark => write!(f, "Dark"),
+        }
+    }
+}
+
+/// Steps generated from a [`DesignSpec`] and what was placed on their wells.
+#[derive(Debug, Clone)]
+pub struct Design {
+    pub steps: Vec<MicroPlate>,
+    /// Treatment of every placed well as its replicate group. Dark controls have the
+    /// [`SampleRole::Control`] role, which tells them apart from unused wells.
+    pub samples: Samples,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DesignError {
+    NoTreatments,
+    NoBrightnessLevels,
+    NoReplicates,
+    TooManyWells { required: usize, available: usize },
+}
+
+impl fmt::Display for DesignError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DesignError::NoTreatments => write!(f, "Select at least one wavelength or the dark control."),
+            DesignError::NoBrightnessLevels => write!(f, "Add at least one brightness level."),
+            DesignError::NoReplicates => write!(f, "The replicate count must be at least 1."),
+            DesignError::TooManyWells { required, available } => write!(
+                f,
+                "The design needs {} wells but the plate only has {} usable wells.",
+                required, available
+            ),
+        }
+    }
+}
+
+/// Factors, replicate count and seed of an experimental design.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DesignSpec {
+    pub wavelengths: Vec<Wavelength>,
+    pub brightness_levels: Vec<f32>,
+    pub dark_control: bool,
+    pub replicates: usize,
+    pub layout: Layout,
+    pub duration: u64,
+    pub seed: i64,
+}
+
+impl Default for DesignSpec {
+    fn default() -> Self {
+        Self {
+            wavelengths: Wavelength::ALL.to_vec(),
+            brightness_levels: vec![50.0],
+            dark_control: false,
+            replicates: 4,
+            layout: Layout::default(),
+            duration: 5,
+            seed: new_seed(),
+        }
+    }
+}
+
+impl DesignSpec {
+    /// Returns the treatments of the design, the dark control last.
+    pub fn treatments(&self) -> Vec<Treatment> {
+        let mut treatments: Vec<Treatment> = self
+            .wavelengths
+            .iter()
+            .cloned()
+            .map(Treatment::Light)
+            .collect();
+
+        if self.dark_control {
+            treatments.push(Treatment::Dark);
+        }
+
+        treatments
+    }
+
+    /// Generates one step per brightness level, every step sharing the same layout.
+    pub fn generate(&self, geometry: &PlateGeometry) -> Result<Design, DesignError> {
+        let treatments = self.treatments();
+        if treatments.is_empty() {
+            return Err(DesignError::NoTreatments);
+        }
+        if self.brightness_levels.is_empty() {
+            return Err(DesignError::NoBrightnessLevels);
+        }
+        if self.replicates == 0 {
+            return Err(DesignError::NoReplicates);
+        }
+
+        let template = MicroPlate::with_geometry(geometry.clone());
+        let usable = template.usable_wells();
+        let required = treatments.len() * self.replicates;
+        if required > usable.len() {
+            return Err(DesignError::TooManyWells { required, available: usable.len() });
+        }
+
+        let mut rng = SplitMix64::new(self.seed as u64);
+        let assignment = match self.layout {
+            Layout::Randomised => {
+                let mut wells = usable;
+                rng.shuffle(&mut wells);
+
+                let replicated = treatments
+                    .iter()
+                    .flat_map(|treatment| std::iter::repeat_n(treatment, self.replicates));
+
+                wells.into_iter().zip(replicated).collect::<Vec<_>>()
+            }
+            Layout::Blocked => usable
+                .chunks(treatments.len())
+                .take(self.replicates)
+                .flat_map(|block| {
+                    let mut shuffled: Vec<&Treatment> = treatments.iter().collect();
+                    rng.shuffle(&mut shuffled);
+                    block.iter().copied().zip(shuffled).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+        };
+
+        let steps = self
+            .brightness_levels
+            .iter()
+            .map(|brightness| {
+                let mut plate = MicroPlate {
+                    brightness: *brightness,
+                    duration: self.duration,
+                    design: Some(self.clone()),
+                    ..template.clone()
+                };
+                if let Some(wavelength) = self.wavelengths.first() {
+                    plate.wavelength = wavelength.clone();
+                }
+
+                plate.wells.iter_mut().for_each(|well| well.led_on = false);
+                for (idx, treatment) in &assignment {
+                    let well = &mut plate.wells[*idx];
+                    match treatment {
+                        Treatment::Light(wavelength) => {
+                            well.led_on = true;
+                            well.wavelength = wavelength.clone();
+                        }
+                        Treatment::Dark => well.led_on = false,
+                    }
+                }
+
+                plate
+            })
+            .collect();
+
+        let samples = assignment
+            .iter()
+            .map(|(idx, treatment)| {
+                let role = match treatment {
+                    Treatment::Light(_) => SampleRole::Sample,
+                    Treatment::Dark => SampleRole::Control,
+                };
+                let sample = SampleInfo { replicate_group: treatment.to_string(), role, ..Default::default() };
+                (template.wells[*idx].label.clone(), sample)
+            })
+            .collect();
+
+        Ok(Design { steps, samples })
+    }
+}
+
+/// Returns a fresh seed derived from the system clock.
+pub fn new_seed() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as i64)
+        .unwrap_or_default()
+}
+
+/// Small deterministic PRNG, so that a seed always regenerates the same layout.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Fisher-Yates shuffle.
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Window used to configure a [`DesignSpec`] and turn it into a sequence.
+pub struct DesignWizard {
+    pub open: bool,
+    spec: DesignSpec,
+    seed_text: String,
+    error: Option<String>,
+}
+
+/// What the user asked the wizard to do with the generated design.
+pub enum DesignAction {
+    Replace(Design),
+    Append(Design),
+}
+
+impl Default for DesignWizard {
+    fn default() -> Self {
+        let spec = DesignSpec::default();
+        Self {
+            open: false,
+            seed_text: spec.seed.to_string(),
+            spec,
+            error: None,
+        }
+    }
+}
+
+impl DesignWizard {
+    /// Shows the wizard, generating plates laid out as `geometry`.
+    pub fn show(&mut self, ctx: &Context, geometry: &PlateGeometry) -> Option<DesignAction> {
+        let mut action = None;
+        let mut open = self.open;
+
+        egui::Window::new("Design Wizard")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                action = self.show_contents(ui, geometry);
+            });
+
+        self.open = open && action.is_none();
+        action
+    }
+
+    fn show_contents(&mut self, ui: &mut Ui, geometry: &PlateGeometry) -> Option<DesignAction> {
+        egui::Grid::new("design_grid")
+            .num_columns(2)
+            .spacing([40.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("wavelengths: ");
+                ui.horizontal(|ui| {
+                    for wavelength in Wavelength::ALL {
+                        let mut selected = self.spec.wavelengths.contains(&wavelength);
+                        if ui.checkbox(&mut selected, wavelength.to_string()).changed() {
+                            if selected {
+                                self.spec.wavelengths.push(wavelength);
+                            } else {
+                                self.spec.wavelengths.retain(|w| *w != wavelength);
+                            }
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("dark control: ");
+                ui.checkbox(&mut self.spec.dark_control, "");
+                ui.end_row();
+
+                ui.label("brightness levels: ");
+                ui.vertical(|ui| {
+                    let mut removed = None;
+                    for (idx, brightness) in self.spec.brightness_levels.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::Slider::new(brightness, 0.0..=100.0).suffix("%"));
+                            if ui.small_button("x").clicked() {
+                                removed = Some(idx);
+                            }
+                        });
+                    }
+                    if let Some(idx) = removed {
+                        self.spec.brightness_levels.remove(idx);
+                    }
+                    if ui.small_button("Add Level").clicked() {
+                        self.spec.brightness_levels.push(50.0);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("replicates: ");
+                ui.add(egui::DragValue::new(&mut self.spec.replicates).speed(1.0).clamp_range(1..=16));
+                ui.end_row();
+
+                ui.label("layout: ");
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.spec.layout, Layout::Randomised, Layout::Randomised.to_string());
+                    ui.selectable_value(&mut self.spec.layout, Layout::Blocked, Layout::Blocked.to_string());
+                });
+                ui.end_row();
+
+                ui.label("step duration [s]: ");
+                ui.add(egui::DragValue::new(&mut self.spec.duration).speed(1.0));
+                ui.end_row();
+
+                ui.label("seed: ");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.seed_text);
+                    if ui.small_button("New Seed").clicked() {
+                        self.seed_text = new_seed().to_string();
+                    }
+                });
+                ui.end_row();
+            });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        ui.add_space(5.0);
+
+        let mut action = None;
+        ui.horizontal(|ui| {
+            let replace = ui.button("Replace Sequence").clicked();
+            let append = ui.button("Append to Sequence").clicked();
+
+            if replace || append {
+                match self.seed_text.trim().parse::<i64>() {
+                    Ok(seed) => self.spec.seed = seed,
+                    Err(_) => {
+                        self.error = Some(format!("\"{}\" is not a valid seed.", self.seed_text));
+                        return;
+                    }
+                }
+
+                match self.spec.generate(geometry) {
+                    Ok(design) => {
+                        self.error = None;
+                        action = Some(if replace {
+                            DesignAction::Replace(design)
+                        } else {
+                            DesignAction::Append(design)
+                        });
+                    }
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+        });
+
+        action
+    }
+
+    /// Opens the wizard with the spec of an existing design so it can be regenerated.
+    pub fn load_spec(&mut self, spec: DesignSpec) {
+        self.seed_text = spec.seed.to_string();
+        self.spec = spec;
+        self.error = None;
+        self.open = true;
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::fmt::Formatter;
+use serde::{Serialize, Deserialize};
+
+/// How the wells of a plate are labelled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelScheme {
+    /// Rows lettered from `A` and columns numbered from `1`, e.g. `B3`.
+    RowLetterColumnNumber,
+    /// Columns lettered from `first_letter` and rows numbered down to `1`, e.g. `D5`.
+    ColumnLetterRowNumberDescending { first_letter: char },
+}
+
+/// Layout of the wells of a plate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlateGeometry {
+    pub name: String,
+    pub rows: usize,
+    pub columns: usize,
+    /// `(row, column)` positions holding a usable well, every position being usable when `None`.
+    pub populated: Option<Vec<(usize, usize)>>,
+    pub labels: LabelScheme,
+}
+
+impl Default for PlateGeometry {
+    fn default() -> Self {
+        PlateGeometry::tinywell()
+    }
+}
+
+impl fmt::Display for PlateGeometry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl PlateGeometry {
+    /// The 5×5 Tinywell board, where only the 16 wells above a sensor are usable.
+    pub fn tinywell() -> Self {
+        let populated = (0..5)
+            .flat_map(|row| (0..5).map(move |col| (row, col)))
+            .filter(|(row, col)| {
+                !((*col == 1 && *row != 1) || (*col != 1 && *row == 1) || (*row == 3 && *col == 0))
+            })
+            .collect();
+
+        Self {
+            name: "Tinywell (16 wells)".to_string(),
+            rows: 5,
+            columns: 5,
+            populated: Some(populated),
+            labels: LabelScheme::ColumnLetterRowNumberDescending { first_letter: 'D' },
+        }
+    }
+
+    /// A standard microplate with every well usable.
+    pub fn standard(rows: usize, columns: usize) -> Self {
+        Self {
+            name: format!("{}-well plate", rows * columns),
+            rows,
+            columns,
+            populated: None,
+            labels: LabelScheme::RowLetterColumnNumber,
+        }
+    }
+
+    /// Returns the geometries offered in the UI.
+    pub fn presets() -> Vec<PlateGeometry> {
+        vec![
+            PlateGeometry::tinywell(),
+            PlateGeometry::standard(4, 6),
+            PlateGeometry::standard(6, 8),
+            PlateGeometry::standard(8, 12),
+        ]
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows * self.columns
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the row-major index of the well at `row`, `col`.
+    pub fn index(&self, row: usize, col: usize) -> usize {
+        row * self.columns + col
+    }
+
+    /// Returns whether the well at `row`, `col` can be lit and measured.
+    pub fn is_populated(&self, row: usize, col: usize) -> bool {
+        match &self.populated {
+            Some(populated) => populated.contains(&(row, col)),
+            None => row < self.rows && col < self.columns,
+        }
+    }
+
+    /// Returns the label of the well at `row`, `col`.
+    pub fn label(&self, row: usize, col: usize) -> String {
+        match self.labels {
+            LabelScheme::RowLetterColumnNumber => {
+                format!("{}{}", self.row_header(row), self.column_header(col))
+            }
+            LabelScheme::ColumnLetterRowNumberDescending { .. } => {
+                format!("{}{}", self.column_header(col), self.row_header(row))
+            }
+        }
+    }
+
+    /// Returns the part of the well labels naming row `row`.
+    pub fn row_header(&self, row: usize) -> String {
+        match self.labels {
+            LabelScheme::RowLetterColumnNumber => letters(row, 'A'),
+            LabelScheme::ColumnLetterRowNumberDescending { .. } => (self.rows - row).to_string(),
+        }
+    }
+
+    /// Returns the part of the well labels naming column `col`.
+    pub fn column_header(&self, col: usize) -> String {
+        match self.labels {
+            LabelScheme::RowLetterColumnNumber => (col + 1).to_string(),
+            LabelScheme::ColumnLetterRowNumberDescending { first_letter } => letters(col, first_letter),
+        }
+    }
+}
+
+/// Returns the letters of the `idx`th row or column counting from `first`, spreadsheet style.
+fn letters(idx: usize, first: char) -> String {
+    let mut idx = idx + (first as u8 - b'A') as usize + 1;
+    let mut name = String::new();
+    while idx > 0 {
+        let remainder = (idx - 1) % 26;
+        name.insert(0, (b'A' + remainder as u8) as char);
+        idx = (idx - 1) / 26;
+    }
+    name
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use crate::growth::{GrowthFits, GrowthParameter};
+use crate::microplate::MicroPlate;
+use crate::sample::Samples;
+use crate::stats::{one_way_anova, welch_t_test, Summary};
+use crate::timeseries::{by_time, StepSpan, Timeline};
+
+/// Wells of each group, keyed by group name.
+pub type Groups = BTreeMap<String, Vec<String>>;
+
+/// How the wells of a run are sorted into groups to be compared.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// The replicate group of the sample in the well. Wells without one are left out.
+    #[default]
+    ReplicateGroup,
+    /// The light the well receives: its wavelength and the brightness of the step, or dark.
+    Condition,
+}
+
+impl Grouping {
+    pub const ALL: [Grouping; 2] = [Grouping::ReplicateGroup, Grouping::Condition];
+}
+
+impl fmt::Display for Grouping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Grouping::ReplicateGroup => write!(f, "Replicate group"),
+            Grouping::Condition => write!(f, "Condition"),
+        }
+    }
+}
+
+/// A run as needed to sort its wells into groups.
+pub struct GroupedRun<'a> {
+    pub timeline: &'a Timeline,
+    /// Steps of the sequence the run followed, empty when it is not known.
+    pub sequence: &'a [MicroPlate],
+    pub samples: &'a Samples,
+}
+
+impl GroupedRun<'_> {
+    /// Returns the condition of the well `label` during `span`.
+    fn condition(&self, span: &StepSpan, label: &str) -> String {
+        let recorded = self.sequence.get(span.step).and_then(|plate| {
+            plate.wells.iter().find(|well| well.label == label).map(|well| (plate, well))
+        });
+
+        match recorded {
+            Some((_, well)) if !well.led_on => "Dark".to_string(),
+            Some((plate, well)) => format!("{} {}%", well.wavelength, plate.brightness),
+            None => format!("{} {}%", span.wavelength, span.brightness),
+        }
+    }
+
+    fn replicate_group(&self, label: &str) -> Option<String> {
+        self.samples
+            .get(label)
+            .map(|sample| sample.replicate_group.clone())
+            .filter(|group| !group.is_empty())
+    }
+
+    /// Returns the wells of every group `time` seconds into the run.
+    pub fn groups_at(&self, grouping: Grouping, time: f64) -> Groups {
+        let span = self.timeline.step_at(time);
+        let mut groups = Groups::new();
+        for label in self.timeline.series.keys() {
+            let group = match grouping {
+                Grouping::ReplicateGroup => self.replicate_group(label),
+                Grouping::Condition => span.map(|span| self.condition(span, label)),
+            };
+            if let Some(group) = group {
+                groups.entry(group).or_default().push(label.clone());
+            }
+        }
+        groups
+    }
+
+    /// Returns the wells of every group over the whole run.
+    pub fn groups(&self, grouping: Grouping) -> Groups {
+        let mut groups = Groups::new();
+        for label in self.timeline.series.keys() {
+            let group = match grouping {
+                Grouping::ReplicateGroup => self.replicate_group(label),
+                Grouping::Condition => {
+                    let mut conditions: Vec<String> = vec![];
+                    for span in &self.timeline.steps {
+                        let condition = self.condition(span, label);
+                        if conditions.last() != Some(&condition) {
+                            conditions.push(condition);
+                        }
+                    }
+                    (!conditions.is_empty()).then(|| conditions.join(" → "))
+                }
+            };
+            if let Some(group) = group {
+                groups.entry(group).or_default().push(label.clone());
+            }
+        }
+        groups
+    }
+
+    /// Returns the summary of the readings of every group at every time they were taken.
+    pub fn summaries_over_time(&self, grouping: Grouping) -> Vec<(f64, String, Summary)> {
+        let rows = by_time(self.timeline.series.iter().map(|(label, series)| (label, series.points())));
+
+        let mut summaries = vec![];
+        for (time, values) in rows {
+            for (group, labels) in self.groups_at(grouping, time) {
+                let values: Vec<f64> = labels.iter().filter_map(|label| values.get(label).copied()).collect();
+                if let Some(summary) = Summary::of(&values) {
+                    summaries.push((time, group, summary));
+                }
+            }
+        }
+        summaries
+    }
+}
+
+/// Returns the values of every group, taking `value` of each of its wells that has one.
+pub fn group_values(groups: &Groups, value: impl Fn(&str) -> Option<f64>) -> BTreeMap<String, Vec<f64>> {
+    groups
+        .iter()
+        .map(|(group, labels)| (group.clone(), labels.iter().filter_map(|label| value(label)).collect::<Vec<f64>>()))
+        .filter(|(_, values)| !values.is_empty())
+        .collect()
+}
+
+/// Returns the `parameter` of the growth curve fitted to the well `label`, if any.
+pub fn fitted(fits: &GrowthFits, parameter: GrowthParameter, label: &str) -> Option<f64> {
+    fits.get(label)?
+        .as_ref()
+        .ok()
+        .and_then(|fit| parameter.estimate(fit))
+        .map(|estimate| estimate.value)
+}
+
+fn summary_cells(summary: &Summary) -> [String; 5] {
+    let optional = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+    [
+        summary.n.to_string(),
+        summary.mean.to_string(),
+        optional(summary.sd),
+        optional(summary.sem()),
+        optional(summary.cv()),
+    ]
+}
+
+const SUMMARY_HEADER: [&str; 5] = ["n", "Mean", "SD", "SEM", "CV (%)"];
+
+/// Returns the summaries of the readings of every group over time, header first.
+pub fn over_time_table(run: &GroupedRun, grouping: Grouping) -> Vec<Vec<String>> {
+    let mut table = vec![["Time (s)", "Group"].iter().chain(&SUMMARY_HEADER).map(|cell| cell.to_string()).collect()];
+    for (time, group, summary) in run.summaries_over_time(grouping) {
+        let mut row = vec![time.to_string(), group];
+        row.extend(summary_cells(&summary));
+        table.push(row);
+    }
+    table
+}
+
+/// Returns the summaries of every growth parameter of every group, header first.
+pub fn parameter_table(groups: &Groups, fits: &GrowthFits) -> Vec<Vec<String>> {
+    let mut table = vec![["Parameter", "Group"].iter().chain(&SUMMARY_HEADER).map(|cell| cell.to_string()).collect()];
+    for parameter in GrowthParameter::ALL {
+        for (group, values) in group_values(groups, |label| fitted(fits, parameter, label)) {
+            if let Some(summary) = Summary::of(&values) {
+                let mut row = vec![parameter.to_string(), group];
+                row.extend(summary_cells(&summary));
+                table.push(row);
+            }
+        }
+    }
+    table
+}
+
+/// Returns the tests of the differences between groups of every growth parameter, header first.
+pub fn tests_table(groups: &Groups, fits: &GrowthFits) -> Vec<Vec<String>> {
+    let mut table = vec![["Parameter", "Test", "Groups", "Statistic", "df", "p"].map(str::to_string).to_vec()];
+    for parameter in GrowthParameter::ALL {
+        let values = group_values(groups, |label| fitted(fits, parameter, label));
+        let names: Vec<&String> = values.keys().collect();
+        let samples: Vec<Vec<f64>> = values.values().cloned().collect();
+
+        if let ([a, b], [first, second]) = (samples.as_slice(), names.as_slice()) {
+            if let Some(test) = welch_t_test(a, b) {
+                table.push(vec![
+                    parameter.to_string(),
+                    "Welch's t-test".to_string(),
+                    format!("{} vs {}", first, second),
+                    format!("t = {}", test.t),
+                    test.df.to_string(),
+                    test.p.to_string(),
+                ]);
+            }
+        }
+
+        if let Some(anova) = one_way_anova(&samples) {
+            table.push(vec![
+                parameter.to_string(),
+                "One-way ANOVA".to_string(),
+                "All groups".to_string(),
+                format!("F = {}", anova.f),
+                format!("{}, {}", anova.df_between, anova.df_within),
+                anova.p.to_string(),
+            ]);
+            for comparison in &anova.comparisons {
+                table.push(vec![
+                    parameter.to_string(),
+                    "Pairwise t-test (Holm)".to_string(),
+                    format!("{} vs {}", names[comparison.first], names[comparison.second]),
+                    format!("Δ = {}", comparison.difference),
+                    anova.df_within.to_string(),
+                    comparison.p.to_string(),
+                ]);
+            }
+        }
+    }
+    table
+}
+
+/// Writes the statistics of the groups of `run` to a workbook at `path`.
+pub fn export(path: &Path, run: &GroupedRun, grouping: Grouping, fits: &GrowthFits) -> Result<(), XlsxError> {
+    let groups = run.groups(grouping);
+    let sheets = [
+        ("Over Time", over_time_table(run, grouping)),
+        ("Growth Parameters", parameter_table(&groups, fits)),
+        ("Tests", tests_table(&groups, fits)),
+    ];
+
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    for (name, table) in sheets {
+        let worksheet = workbook.add_worksheet().set_name(name)?;
+        for (row, cells) in table.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if row == 0 {
+                    worksheet.write_string_with_format(row as u32, column as u16, cell, &bold)?;
+                } else if let Some(number) = cell.parse::<f64>().ok().filter(|number| number.is_finite()) {
+                    worksheet.write_number(row as u32, column as u16, number)?;
+                } else {
+                    worksheet.write_string(row as u32, column as u16, cell)?;
+                }
+            }
+        }
+    }
+    workbook.save(path)
+}
@@ -0,0 +1,495 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::f64::consts::{E, LN_2};
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+use crate::stats::student_t_quantile;
+use crate::timeseries::Timeline;
+
+/// Maximum number of points a curve is fitted to. Longer series are averaged down to it.
+const MAX_FIT_POINTS: usize = 500;
+/// Number of readings averaged into the baseline the growth is relative to.
+const BASELINE_READINGS: usize = 3;
+/// Smallest change of `ln(y / y0)` considered growth.
+const MIN_GROWTH: f64 = 0.01;
+const MAX_ITERATIONS: usize = 200;
+
+/// Sigmoid model of growth, parametrised as in Zwietering et al. (1990).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthModel {
+    Logistic,
+    #[default]
+    Gompertz,
+    /// Generalises the logistic model with a shape parameter `ν`.
+    Richards,
+}
+
+impl GrowthModel {
+    pub const ALL: [GrowthModel; 3] = [GrowthModel::Logistic, GrowthModel::Gompertz, GrowthModel::Richards];
+
+    /// Returns `ln(y / y0)` at `t` hours for the parameters `[A, μ, λ]`, followed by `ν` for the
+    /// Richards model.
+    pub fn evaluate(&self, params: &[f64], t: f64) -> f64 {
+        let (a, mu, lambda) = (params[0], params[1], params[2]);
+        match self {
+            GrowthModel::Logistic => a / (1.0 + (4.0 * mu / a * (lambda - t) + 2.0).exp()),
+            GrowthModel::Gompertz => a * (-(mu * E / a * (lambda - t) + 1.0).exp()).exp(),
+            GrowthModel::Richards => {
+                let nu = params[3];
+                let exponent = mu / a * (1.0 + nu).powf(1.0 + 1.0 / nu) * (lambda - t);
+                a * (1.0 + nu * (1.0 + nu).exp() * exponent.exp()).powf(-1.0 / nu)
+            }
+        }
+    }
+
+    /// Returns the fitted `ln(y / y0)` for the parameters of the model followed by an offset,
+    /// which absorbs the difference between the baseline and the start of the curve.
+    fn predict(&self, params: &[f64], t: f64) -> f64 {
+        self.evaluate(params, t) + params[params.len() - 1]
+    }
+
+    /// Returns the number of parameters fitted, including the offset.
+    fn parameter_count(&self) -> usize {
+        match self {
+            GrowthModel::Logistic | GrowthModel::Gompertz => 4,
+            GrowthModel::Richards => 5,
+        }
+    }
+
+    /// Keeps the shape parameter of the Richards model where the model is defined and stable.
+    fn constrain(&self, params: &mut [f64]) {
+        if *self == GrowthModel::Richards {
+            params[3] = params[3].clamp(0.01, 50.0);
+        }
+    }
+}
+
+impl fmt::Display for GrowthModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GrowthModel::Logistic => write!(f, "Logistic"),
+            GrowthModel::Gompertz => write!(f, "Gompertz"),
+            GrowthModel::Richards => write!(f, "Richards"),
+        }
+    }
+}
+
+/// Errors raised while fitting a growth curve to the readings of a well.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FitError {
+    /// There are no more readings than parameters of the model.
+    TooFewPoints,
+    /// The baseline reading is zero or negative, so the growth relative to it is undefined.
+    NonPositiveBaseline,
+    /// The readings never move away from the baseline.
+    NoGrowth,
+    /// The parameters could not be estimated from the readings.
+    NoConvergence,
+}
+
+impl fmt::Display for FitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FitError::TooFewPoints => write!(f, "Not enough readings to fit the model."),
+            FitError::NonPositiveBaseline => write!(f, "The baseline reading is not positive."),
+            FitError::NoGrowth => write!(f, "The readings do not change from the baseline."),
+            FitError::NoConvergence => write!(f, "The fit did not converge."),
+        }
+    }
+}
+
+impl Error for FitError {}
+
+/// Estimated value of a parameter with the half-width of its 95 % confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub value: f64,
+    pub margin: f64,
+}
+
+impl Estimate {
+    pub fn contains(&self, value: f64) -> bool {
+        (value - self.value).abs() <= self.margin
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} ± {:.3}", self.value, self.margin)
+    }
+}
+
+/// Growth curve fitted to the logarithm of the readings of a well relative to the first ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthFit {
+    pub model: GrowthModel,
+    /// Mean of the first readings, which the growth is relative to.
+    pub baseline: f64,
+    params: Vec<f64>,
+    /// Times of the first and last readings fitted, in seconds into the run.
+    span: (f64, f64),
+    /// Time before exponential growth starts, in hours.
+    pub lag_time: Estimate,
+    /// Maximum specific growth rate, per hour.
+    pub max_growth_rate: Estimate,
+    /// Time the culture takes to double at the maximum growth rate, in hours.
+    pub doubling_time: Option<Estimate>,
+    /// Reading the curve levels off at.
+    pub carrying_capacity: Estimate,
+    pub r_squared: f64,
+}
+
+/// Fits of the wells of a run, keyed by well label.
+pub type GrowthFits = BTreeMap<String, Result<GrowthFit, FitError>>;
+
+/// Parameter derived from a growth curve.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthParameter {
+    LagTime,
+    #[default]
+    MaxGrowthRate,
+    DoublingTime,
+    CarryingCapacity,
+}
+
+impl GrowthParameter {
+    pub const ALL: [GrowthParameter; 4] = [
+        GrowthParameter::LagTime,
+        GrowthParameter::MaxGrowthRate,
+        GrowthParameter::DoublingTime,
+        GrowthParameter::CarryingCapacity,
+    ];
+
+    /// Returns the estimate of the parameter in `fit`, if it has one.
+    pub fn estimate(&self, fit: &GrowthFit) -> Option<Estimate> {
+        match self {
+            GrowthParameter::LagTime => Some(fit.lag_time),
+            GrowthParameter::MaxGrowthRate => Some(fit.max_growth_rate),
+            GrowthParameter::DoublingTime => fit.doubling_time,
+            GrowthParameter::CarryingCapacity => Some(fit.carrying_capacity),
+        }
+    }
+}
+
+impl fmt::Display for GrowthParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GrowthParameter::LagTime => write!(f, "Lag time (h)"),
+            GrowthParameter::MaxGrowthRate => write!(f, "Max specific growth rate (1/h)"),
+            GrowthParameter::DoublingTime => write!(f, "Doubling time (h)"),
+            GrowthParameter::CarryingCapacity => write!(f, "Carrying capacity"),
+        }
+    }
+}
+
+impl GrowthFit {
+    /// Fits `model` to `points`, pairs of the time in seconds and the reading.
+    pub fn fit(model: GrowthModel, points: &[[f64; 2]]) -> Result<GrowthFit, FitError> {
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first[0], last[0]),
+            _ => return Err(FitError::TooFewPoints),
+        };
+
+        let baseline = points.iter().take(BASELINE_READINGS).map(|point| point[1]).sum::<f64>()
+            / points.len().min(BASELINE_READINGS) as f64;
+        if baseline <= 0.0 {
+            return Err(FitError::NonPositiveBaseline);
+        }
+
+        let growth: Vec<[f64; 2]> = points
+            .iter()
+            .filter(|point| point[1] > 0.0)
+            .map(|point| [point[0] / 3600.0, (point[1] / baseline).ln()])
+            .collect();
+        let growth = thin(&growth, MAX_FIT_POINTS);
+
+        let count = model.parameter_count();
+        if growth.len() <= count {
+            return Err(FitError::TooFewPoints);
+        }
+
+        let initial = initial_guess(model, &growth).ok_or(FitError::NoGrowth)?;
+        let (params, ssr, covariance) = levenberg_marquardt(model, &growth, initial)
+            .ok_or(FitError::NoConvergence)?;
+
+        // Confidence intervals from the asymptotic covariance of the least-squares estimates
+        let df = (growth.len() - count) as f64;
+        let variance = ssr / df;
+        let critical = student_t_quantile(0.975, df);
+        let estimate = |idx: usize| Estimate {
+            value: params[idx],
+            margin: critical * (variance * covariance[idx][idx]).sqrt(),
+        };
+
+        // The curve levels off at the asymptote above the offset
+        let offset = count - 1;
+        let level = params[0] + params[offset];
+        let level_margin = critical
+            * (variance * (covariance[0][0] + covariance[offset][offset] + 2.0 * covariance[0][offset])).max(0.0).sqrt();
+        let rate = estimate(1);
+        let capacity = baseline * level.exp();
+        let mean = growth.iter().map(|point| point[1]).sum::<f64>() / growth.len() as f64;
+        let sst: f64 = growth.iter().map(|point| (point[1] - mean).powi(2)).sum();
+
+        Ok(GrowthFit {
+            model,
+            baseline,
+            span: (first, last),
+            lag_time: estimate(2),
+            max_growth_rate: rate,
+            doubling_time: (rate.value > 0.0).then(|| Estimate {
+                value: LN_2 / rate.value,
+                margin: LN_2 / rate.value.powi(2) * rate.margin,
+            }),
+            carrying_capacity: Estimate { value: capacity, margin: capacity * level_margin },
+            r_squared: if sst > 0.0 { 1.0 - ssr / sst } else { 1.0 },
+            params,
+        })
+    }
+
+    /// Returns the reading predicted `time` seconds into the run.
+    pub fn value_at(&self, time: f64) -> f64 {
+        self.baseline * self.model.predict(&self.params, time / 3600.0).exp()
+    }
+
+    /// Returns `count` points of the fitted curve evenly spaced over the readings fitted.
+    pub fn curve(&self, count: usize) -> Vec<[f64; 2]> {
+        let (start, end) = self.span;
+        (0..count)
+            .map(|idx| {
+                let time = start + (end - start) * idx as f64 / (count.max(2) - 1) as f64;
+                [time, self.value_at(time)]
+            })
+            .collect()
+    }
+}
+
+/// Fits `model` to the readings of every well of `timeline`.
+pub fn fit_timeline(model: GrowthModel, timeline: &Timeline) -> GrowthFits {
+    timeline
+        .series
+        .iter()
+        .map(|(label, series)| (label.clone(), GrowthFit::fit(model, &series.points())))
+        .collect()
+}
+
+/// Returns `fits` as rows of cells, starting with the header.
+pub fn to_table(fits: &GrowthFits) -> Vec<Vec<String>> {
+    let mut table = vec![[
+        "Well",
+        "Model",
+        "Lag time (h)",
+        "Lag time CI (±)",
+        "Max specific growth rate (1/h)",
+        "Max specific growth rate CI (±)",
+        "Doubling time (h)",
+        "Doubling time CI (±)",
+        "Carrying capacity",
+        "Carrying capacity CI (±)",
+        "R²",
+        "Error",
+    ]
+    .map(str::to_string)
+    .to_vec()];
+
+    for (label, fit) in fits {
+        let row = match fit {
+            Ok(fit) => {
+                let mut row = vec![label.clone(), fit.model.to_string()];
+                for estimate in [Some(fit.lag_time), Some(fit.max_growth_rate), fit.doubling_time, Some(fit.carrying_capacity)] {
+                    match estimate {
+                        Some(estimate) => row.extend([estimate.value.to_string(), estimate.margin.to_string()]),
+                        None => row.extend([String::new(), String::new()]),
+                    }
+                }
+                row.extend([fit.r_squared.to_string(), String::new()]);
+                row
+            }
+            Err(e) => {
+                let mut row = vec![label.clone()];
+                row.resize(11, String::new());
+                row.push(e.to_string());
+                row
+            }
+        };
+        table.push(row);
+    }
+    table
+}
+
+/// Writes the table of `fits` to the CSV file at `path`.
+pub fn export(path: &Path, fits: &GrowthFits) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in to_table(fits) {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Averages consecutive points of `points` so that at most `max` are left.
+fn thin(points: &[[f64; 2]], max: usize) -> Vec<[f64; 2]> {
+    let chunk = points.len().div_ceil(max).max(1);
+    points
+        .chunks(chunk)
+        .map(|chunk| {
+            let n = chunk.len() as f64;
+            [chunk.iter().map(|p| p[0]).sum::<f64>() / n, chunk.iter().map(|p| p[1]).sum::<f64>() / n]
+        })
+        .collect()
+}
+
+/// Estimates the parameters of `model` from the shape of `points`, or returns `None` when they
+/// show no growth.
+fn initial_guess(model: GrowthModel, points: &[[f64; 2]]) -> Option<Vec<f64>> {
+    let asymptote = points
+        .iter()
+        .map(|point| point[1])
+        .fold(0.0, |extreme: f64, y| if y.abs() > extreme.abs() { y } else { extreme });
+    if asymptote.abs() < MIN_GROWTH {
+        return None;
+    }
+
+    // Steepest slope in the direction of growth, over a window long enough to smooth noise
+    let window = (points.len() / 20).max(1);
+    let (idx, rate) = (0..points.len() - window)
+        .map(|idx| {
+            let (a, b) = (points[idx], points[idx + window]);
+            (idx, (b[1] - a[1]) / (b[0] - a[0]))
+        })
+        .filter(|(_, rate)| rate.is_finite())
+        .max_by(|(_, a), (_, b)| (a * asymptote.signum()).total_cmp(&(b * asymptote.signum())))?;
+    if rate * asymptote <= 0.0 {
+        return None;
+    }
+
+    let [time, value] = points[idx];
+    let lag = (time - value / rate).clamp(0.0, points[points.len() - 1][0]);
+
+    let mut params = vec![asymptote, rate, lag];
+    if model == GrowthModel::Richards {
+        params.push(1.0);
+    }
+    params.push(0.0);
+    Some(params)
+}
+
+/// Fits `model` to `points` by least squares, starting from `params`. Returns the parameters,
+/// the sum of squared residuals and the inverse of `JᵀJ` at the optimum.
+fn levenberg_marquardt(
+    model: GrowthModel,
+    points: &[[f64; 2]],
+    mut params: Vec<f64>,
+) -> Option<(Vec<f64>, f64, Vec<Vec<f64>>)> {
+    let ssr = |params: &[f64]| -> f64 {
+        points.iter().map(|[t, y]| (y - model.predict(params, *t)).powi(2)).sum()
+    };
+
+    let mut current = ssr(&params);
+    let mut damping = 1e-3;
+    for _ in 0..MAX_ITERATIONS {
+        let (jtj, jtr) = normal_equations(model, points, &params);
+
+        let mut improved = false;
+        while damping < 1e12 {
+            let mut damped = jtj.clone();
+            for (idx, row) in damped.iter_mut().enumerate() {
+                row[idx] += damping * jtj[idx][idx].max(1e-12);
+            }
+
+            if let Some(step) = solve(damped, jtr.clone()) {
+                let mut candidate: Vec<f64> = params.iter().zip(&step).map(|(p, d)| p + d).collect();
+                model.constrain(&mut candidate);
+                let value = ssr(&candidate);
+                if value.is_finite() && value < current {
+                    let relative = (current - value) / current.max(f64::MIN_POSITIVE);
+                    params = candidate;
+                    current = value;
+                    damping = (damping / 10.0).max(1e-12);
+                    improved = relative > 1e-12;
+                    break;
+                }
+            }
+            damping *= 10.0;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    let (jtj, _) = normal_equations(model, points, &params);
+    let covariance = invert(jtj)?;
+    let valid = params.iter().all(|p| p.is_finite())
+        && (0..params.len()).all(|idx| covariance[idx][idx].is_finite() && covariance[idx][idx] >= 0.0);
+    valid.then_some((params, current, covariance))
+}
+
+/// Returns `JᵀJ` and `Jᵀr` for the Jacobian `J` of `model` at `params`, estimated by finite
+/// differences, and the residuals `r` of `points`.
+fn normal_equations(model: GrowthModel, points: &[[f64; 2]], params: &[f64]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let count = params.len();
+    let mut jtj = vec![vec![0.0; count]; count];
+    let mut jtr = vec![0.0; count];
+
+    for [t, y] in points {
+        let value = model.predict(params, *t);
+        let gradient: Vec<f64> = (0..count)
+            .map(|idx| {
+                let h = 1e-6 * params[idx].abs().max(1e-3);
+                let mut shifted = params.to_vec();
+                shifted[idx] += h;
+                (model.predict(&shifted, *t) - value) / h
+            })
+            .collect();
+
+        for row in 0..count {
+            jtr[row] += gradient[row] * (y - value);
+            for column in 0..count {
+                jtj[row][column] += gradient[row] * gradient[column];
+            }
+        }
+    }
+    (jtj, jtr)
+}
+
+/// Solves `a x = b` by Gaussian elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|i, j| a[*i][column].abs().total_cmp(&a[*j][column].abs()))?;
+        if a[pivot][column].abs() < 1e-300 {
+            return None;
+        }
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        let pivot_row = a[column].clone();
+        for row in column + 1..n {
+            let factor = a[row][column] / pivot_row[column];
+            for (value, pivot) in a[row][column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    x.iter().all(|value| value.is_finite()).then_some(x)
+}
+
+fn invert(a: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let columns = (0..n)
+        .map(|column| {
+            let unit = (0..n).map(|row| if row == column { 1.0 } else { 0.0 }).collect();
+            solve(a.clone(), unit)
+        })
+        .collect::<Option<Vec<Vec<f64>>>>()?;
+    Some((0..n).map(|row| (0..n).map(|column| columns[column][row]).collect()).collect())
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use std::fmt::Formatter;
+use serde::{Serialize, Deserialize};
+use crate::geometry::PlateGeometry;
+use crate::microplate::MicroPlate;
+
+/// Multiplexer and channel of a light sensor, which frames and sensor commands address it by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SensorId {
+    pub mux: u8,
+    pub sensor: u8,
+}
+
+impl fmt::Display for SensorId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "sensor {} of multiplexer {}", self.sensor, self.mux)
+    }
+}
+
+/// Wiring of a single well to its light sensor and LEDs on the boards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Channel {
+    /// Label of the well on the Tinywell board, e.g. `D5`.
+    pub label: String,
+    /// I2C multiplexer the sensor sits behind, `0` for `0x70` and `1` for `0x77`.
+    pub mux: u8,
+    /// Multiplexer channel of the sensor.
+    pub sensor: u8,
+    /// Index of the first of the four LEDs of the well in the shift registers.
+    pub led: u8,
+}
+
+/// Describes how the wells of the plate are wired to the Teensyduino.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareMapping {
+    /// Layout of the wired plate, steps laid out otherwise are not wired.
+    #[serde(default)]
+    pub geometry: PlateGeometry,
+    pub channels: Vec<Channel>,
+}
+
+impl Channel {
+    /// Returns the sensor of the well.
+    pub fn id(&self) -> SensorId {
+        SensorId { mux: self.mux, sensor: self.sensor }
+    }
+}
+
+impl Default for HardwareMapping {
+    fn default() -> Self {
+        let channel = |label: &str, mux, sensor, led: u8| Channel {
+            label: label.to_string(),
+            mux,
+            sensor,
+            led: led * 4,
+        };
+
+        Self {
+            geometry: PlateGeometry::tinywell(),
+            channels: vec![
+                channel("D5", 1, 2, 15),
+                channel("F5", 1, 7, 14),
+                channel("G5", 0, 0, 3),
+                channel("H5", 0, 7, 2),
+                channel("E4", 1, 1, 12),
+                channel("D3", 1, 0, 11),
+                channel("F3", 1, 6, 13),
+                channel("G3", 0, 3, 0),
+                channel("H3", 0, 6, 1),
+                channel("F2", 1, 5, 10),
+                channel("G2", 0, 2, 7),
+                channel("H2", 0, 5, 6),
+                channel("D1", 1, 3, 8),
+                channel("F1", 1, 4, 9),
+                channel("G1", 0, 1, 4),
+                channel("H1", 0, 4, 5),
+            ],
+        }
+    }
+}
+
+impl HardwareMapping {
+    /// Returns whether the wells of plates laid out as `geometry` are wired to the board.
+    pub fn is_wired_for(&self, geometry: &PlateGeometry) -> bool {
+        self.geometry == *geometry
+    }
+
+    /// Returns the channel of `sensor`.
+    pub fn channel(&self, sensor: SensorId) -> Option<&Channel> {
+        self.channels.iter().find(|channel| channel.id() == sensor)
+    }
+
+    /// Returns the index in [`MicroPlate::wells`] of the well of `plate` read by `sensor` of `mux`.
+    pub fn well_for_sensor(&self, plate: &MicroPlate, mux: u8, sensor: u8) -> Option<usize> {
+        if !self.is_wired_for(&plate.geometry) {
+            return None;
+        }
+
+        let channel = self
+            .channels
+            .iter()
+            .find(|channel| channel.mux == mux && channel.sensor == sensor)?;
+        plate.wells.iter().position(|well| well.label == channel.label)
+    }
+
+    /// Returns the channel wired to the well of `plate` at index `idx` of [`MicroPlate::wells`].
+    pub fn channel_for_well(&self, plate: &MicroPlate, idx: usize) -> Option<&Channel> {
+        if !self.is_wired_for(&plate.geometry) {
+            return None;
+        }
+
+        let label = &plate.wells.get(idx)?.label;
+        self.channels.iter().find(|channel| channel.label == *label)
+    }
+}
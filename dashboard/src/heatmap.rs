@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use eframe::egui;
+use eframe::egui::{Color32, Sense};
+use crate::timeseries::Timeline;
+
+/// Samples of the viridis colour map at evenly spaced positions.
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (68, 1, 84),
+    (71, 44, 122),
+    (59, 81, 139),
+    (44, 113, 142),
+    (33, 144, 141),
+    (39, 173, 129),
+    (92, 200, 99),
+    (170, 220, 50),
+    (253, 231, 37),
+];
+
+/// Returns the colour of `t`, clamped to `0..=1`, on the viridis scale.
+pub fn viridis(t: f32) -> Color32 {
+    let position = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f32;
+    let idx = (position.floor() as usize).min(VIRIDIS.len() - 2);
+    let fraction = position - idx as f32;
+
+    let (r0, g0, b0) = VIRIDIS[idx];
+    let (r1, g1, b1) = VIRIDIS[idx + 1];
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * fraction).round() as u8;
+    Color32::from_rgb(mix(r0, r1), mix(g0, g1), mix(b0, b1))
+}
+
+/// Formats a value of a scale reaching `largest` in magnitude.
+fn format_absolute(value: f64, largest: f64) -> String {
+    if largest < 10.0 {
+        format!("{:.3}", value)
+    } else {
+        format!("{:.0}", value)
+    }
+}
+
+/// What the colour of a well represents.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapScale {
+    /// The reading itself, on a scale spanning every well.
+    #[default]
+    Absolute,
+    /// The reading divided by the first reading of the same well.
+    Baseline,
+}
+
+/// Colour and value shown in a well by the heatmap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WellShade {
+    pub color: Color32,
+    pub text: String,
+}
+
+/// Wells coloured by their reading at a point of a run.
+#[derive(Debug, Clone, Default)]
+pub struct Shading {
+    pub wells: BTreeMap<String, WellShade>,
+    /// Values at both ends of the colour scale.
+    pub range: Option<(f64, f64)>,
+    pub scale: HeatmapScale,
+}
+
+/// Display mode colouring each well by its reading.
+#[derive(Debug, Default)]
+pub struct Heatmap {
+    pub enabled: bool,
+    pub scale: HeatmapScale,
+}
+
+impl Heatmap {
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Heatmap");
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.radio_value(&mut self.scale, HeatmapScale::Absolute, "Absolute");
+            ui.radio_value(&mut self.scale, HeatmapScale::Baseline, "Relative to Baseline");
+        });
+    }
+
+    /// Returns the colours of the wells of `timeline` at `time` seconds into the run.
+    pub fn shading(&self, timeline: &Timeline, time: f64) -> Shading {
+        if !self.enabled {
+            return Shading::default();
+        }
+
+        let values: BTreeMap<&String, f64> = timeline
+            .series
+            .iter()
+            .filter_map(|(label, series)| {
+                let value = series.value_at(time)?;
+                match self.scale {
+                    HeatmapScale::Absolute => Some((label, value)),
+                    HeatmapScale::Baseline => {
+                        let baseline = series.first()?[1];
+                        (baseline != 0.0).then(|| (label, value / baseline))
+                    }
+                }
+            })
+            .collect();
+
+        let range = values.values().fold(None, |range: Option<(f64, f64)>, value| match range {
+            Some((min, max)) => Some((min.min(*value), max.max(*value))),
+            None => Some((*value, *value)),
+        });
+        let Some((min, max)) = range else {
+            return Shading::default();
+        };
+
+        let wells = values
+            .into_iter()
+            .map(|(label, value)| {
+                let t = if max > min { (value - min) / (max - min) } else { 0.5 };
+                let text = match self.scale {
+                    HeatmapScale::Absolute => format_absolute(value, min.abs().max(max.abs())),
+                    HeatmapScale::Baseline => format!("{:.2}", value),
+                };
+                (label.clone(), WellShade { color: viridis(t as f32), text })
+            })
+            .collect();
+
+        Shading { wells, range, scale: self.scale }
+    }
+}
+
+impl Shading {
+    /// Draws the colour scale with the values at both of its ends.
+    pub fn show_legend(&self, ui: &mut egui::Ui) {
+        let Some((min, max)) = self.range else {
+            return;
+        };
+        let format = |value: f64| match self.scale {
+            HeatmapScale::Absolute => format_absolute(value, min.abs().max(max.abs())),
+            HeatmapScale::Baseline => format!("{:.2}×", value),
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format(min));
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(200.0, 12.0), Sense::hover());
+            let steps = 50;
+            for step in 0..steps {
+                let left = rect.left() + rect.width() * step as f32 / steps as f32;
+                let right = rect.left() + rect.width() * (step + 1) as f32 / steps as f32;
+                ui.painter().rect_filled(
+                    egui::Rect::from_x_y_ranges(left..=right, rect.y_range()),
+                    0.0,
+                    viridis(step as f32 / (steps - 1) as f32),
+                );
+            }
+            ui.label(format(max));
+            if self.scale == HeatmapScale::Baseline {
+                ui.label("of baseline");
+            }
+        });
+    }
+}
@@ -0,0 +1,231 @@
+use crate::microplate::MicroPlate;
+use crate::sample::Samples;
+
+/// Number of edits kept before the oldest ones are forgotten.
+const MAX_EDITS: usize = 200;
+
+/// A reversible change to the steps or samples of a sequence.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// The settings or wells of the step at `index` changed.
+    Step { index: usize, before: Box<MicroPlate>, after: Box<MicroPlate> },
+    /// `steps` were inserted before the step at `index`.
+    Insert { index: usize, steps: Vec<MicroPlate> },
+    /// The step at `from` was moved so that it ends up at `to`.
+    Move { from: usize, to: usize },
+    /// The step at `index` was removed.
+    Remove { index: usize, step: Box<MicroPlate> },
+    /// The whole sequence was replaced, e.g. by the design wizard or a step table import.
+    Replace { before: Vec<MicroPlate>, after: Vec<MicroPlate> },
+    /// The samples of the wells changed.
+    Samples { before: Samples, after: Samples },
+    /// Several edits made by a single action, applied in order.
+    Group(Vec<Edit>),
+}
+
+impl Edit {
+    /// Applies the edit to `sequence` and `samples` and returns the index of the step to show.
+    fn apply(&self, sequence: &mut Vec<MicroPlate>, samples: &mut Samples) -> usize {
+        match self {
+            Edit::Step { index, after, .. } => {
+                sequence[*index] = (**after).clone();
+                *index
+            }
+            Edit::Insert { index, steps } => {
+                sequence.splice(*index..*index, steps.iter().cloned());
+                *index
+            }
+            Edit::Move { from, to } => {
+                let step = sequence.remove(*from);
+                sequence.insert(*to, step);
+                *to
+            }
+            Edit::Remove { index, .. } => {
+                sequence.remove(*index);
+                index.saturating_sub(1).min(sequence.len().saturating_sub(1))
+            }
+            Edit::Replace { after, .. } => {
+                *sequence = after.clone();
+                0
+            }
+            Edit::Samples { after, .. } => {
+                *samples = after.clone();
+                0
+            }
+            Edit::Group(edits) => edits.iter().fold(0, |_, edit| edit.apply(sequence, samples)),
+        }
+    }
+
+    /// Reverts the edit on `sequence` and `samples` and returns the index of the step to show.
+    fn revert(&self, sequence: &mut Vec<MicroPlate>, samples: &mut Samples) -> usize {
+        match self {
+            Edit::Step { index, before, .. } => {
+                sequence[*index] = (**before).clone();
+                *index
+            }
+            Edit::Insert { index, steps } => {
+                sequence.drain(*index..*index + steps.len());
+                index.saturating_sub(1).min(sequence.len().saturating_sub(1))
+            }
+            Edit::Move { from, to } => {
+                let step = sequence.remove(*to);
+                sequence.insert(*from, step);
+                *from
+            }
+            Edit::Remove { index, step } => {
+                sequence.insert(*index, (**step).clone());
+                *index
+            }
+            Edit::Replace { before, .. } => {
+                *sequence = before.clone();
+                0
+            }
+            Edit::Samples { before, .. } => {
+                *samples = before.clone();
+                0
+            }
+            Edit::Group(edits) => edits.iter().rev().fold(0, |_, edit| edit.revert(sequence, samples)),
+        }
+    }
+}
+
+/// Undo and redo stacks of the edits made to a sequence.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Edits that can be undone, the most recent last, each with a unique id.
+    undo: Vec<(u64, Edit)>,
+    redo: Vec<(u64, Edit)>,
+    next_id: u64,
+    /// Id of the most recent edit when the sequence was last saved or opened.
+    saved: Option<u64>,
+    /// Copy of the tracked step as it was after the last recorded edit.
+    snapshot: Option<(usize, MicroPlate)>,
+    /// Copy of the samples as they were after the last recorded edit.
+    samples: Option<Samples>,
+}
+
+impl History {
+    /// Forgets every edit, e.g. after opening another sequence.
+    pub fn clear(&mut self) {
+        *self = History::default();
+    }
+
+    /// Records that the sequence was saved as it currently is.
+    pub fn mark_saved(&mut self) {
+        self.saved = self.undo.last().map(|(id, _)| *id);
+    }
+
+    /// Returns whether any edit was made or undone since [`History::mark_saved`].
+    pub fn is_dirty(&self) -> bool {
+        self.undo.last().map(|(id, _)| *id) != self.saved
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Applies and records `edit`, returning the index of the step to show.
+    pub fn execute(&mut self, edit: Edit, sequence: &mut Vec<MicroPlate>, samples: &mut Samples) -> usize {
+        self.flush(sequence, samples);
+        let current = edit.apply(sequence, samples);
+        self.push(edit);
+        self.snapshot = None;
+        self.samples = Some(samples.clone());
+        current
+    }
+
+    /// Records the changes made to the step at `current` and to `samples` once not `interacting`.
+    pub fn track(&mut self, sequence: &[MicroPlate], samples: &Samples, current: usize, interacting: bool) {
+        if self.samples.is_none() {
+            self.samples = Some(samples.clone());
+        }
+
+        match &self.snapshot {
+            Some((index, _)) if *index == current => {
+                if !interacting {
+                    self.flush(sequence, samples);
+                }
+            }
+            _ => {
+                self.flush(sequence, samples);
+                self.snapshot = sequence.get(current).map(|step| (current, step.clone()));
+            }
+        }
+    }
+
+    /// Reverts the most recent edit and returns the index of the step to show.
+    pub fn undo(&mut self, sequence: &mut Vec<MicroPlate>, samples: &mut Samples) -> Option<usize> {
+        self.flush(sequence, samples);
+        let (id, edit) = self.undo.pop()?;
+        let current = edit.revert(sequence, samples);
+        self.redo.push((id, edit));
+        self.snapshot = None;
+        self.samples = Some(samples.clone());
+        Some(current)
+    }
+
+    /// Applies the most recently undone edit again and returns the index of the step to show.
+    pub fn redo(&mut self, sequence: &mut Vec<MicroPlate>, samples: &mut Samples) -> Option<usize> {
+        self.flush(sequence, samples);
+        let (id, edit) = self.redo.pop()?;
+        let current = edit.apply(sequence, samples);
+        self.undo.push((id, edit));
+        self.snapshot = None;
+        self.samples = Some(samples.clone());
+        Some(current)
+    }
+
+    /// Records the pending changes to the tracked step and the samples.
+    fn flush(&mut self, sequence: &[MicroPlate], samples: &Samples) {
+        if let Some(before) = self.samples.take() {
+            if before != *samples {
+                self.push(Edit::Samples { before, after: samples.clone() });
+            }
+            self.samples = Some(samples.clone());
+        }
+
+        let Some((index, before)) = self.snapshot.take() else {
+            return;
+        };
+        let Some(after) = sequence.get(index) else {
+            return;
+        };
+
+        if !same_settings(&before, after) {
+            self.push(Edit::Step { index, before: Box::new(before), after: Box::new(after.clone()) });
+        }
+        self.snapshot = Some((index, after.clone()));
+    }
+
+    fn push(&mut self, edit: Edit) {
+        self.undo.push((self.next_id, edit));
+        self.next_id += 1;
+        self.redo.clear();
+
+        if self.undo.len() > MAX_EDITS {
+            self.undo.remove(0);
+        }
+    }
+}
+
+/// Returns whether two steps have the same settings, ignoring the measurements of the wells.
+fn same_settings(a: &MicroPlate, b: &MicroPlate) -> bool {
+    a.name == b.name
+        && a.color == b.color
+        && a.brightness == b.brightness
+        && a.wavelength == b.wavelength
+        && a.duration == b.duration
+        && a.design == b.design
+        && a.geometry == b.geometry
+        && a.wells.len() == b.wells.len()
+        && a.wells.iter().zip(b.wells.iter()).all(|(a, b)| {
+            a.label == b.label
+                && a.led_on == b.led_on
+                && a.disabled == b.disabled
+                && a.wavelength == b.wavelength
+        })
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+use crate::sampling::Aggregate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Lit,
+    Dark,
+}
+
+/// LED change the runner has to send to the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Blank,
+    Relight,
+}
+
+/// Periodically blanks every LED so that the sensors can measure the background of each well.
+pub struct DarkInterleave {
+    pub enabled: bool,
+    pub interval: Duration,
+    pub duration: Duration,
+    /// Frames received this soon after a transition are discarded.
+    pub settle: Duration,
+    phase: Phase,
+    phase_start: Instant,
+    blanked: Duration,
+    dark: Vec<Aggregate>,
+    background: Vec<Option<f32>>,
+}
+
+impl Default for DarkInterleave {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Duration::from_secs(60),
+            duration: Duration::from_secs(2),
+            settle: Duration::from_millis(300),
+            phase: Phase::Lit,
+            phase_start: Instant::now(),
+            blanked: Duration::ZERO,
+            dark: vec![],
+            background: vec![],
+        }
+    }
+}
+
+impl DarkInterleave {
+    /// Resets the interleave at the start of a run.
+    pub fn start(&mut self) {
+        self.phase = Phase::Lit;
+        self.phase_start = Instant::now();
+        self.blanked = Duration::ZERO;
+        self.dark.clear();
+        self.background.clear();
+    }
+
+    /// Advances the interleave, returning the LED change to apply if a phase just ended.
+    pub fn update(&mut self) -> Option<Transition> {
+        if !self.enabled {
+            return None;
+        }
+
+        let elapsed = self.phase_start.elapsed();
+        match self.phase {
+            Phase::Lit if elapsed >= self.interval => {
+                self.phase = Phase::Dark;
+                self.phase_start = Instant::now();
+                self.dark.clear();
+                Some(Transition::Blank)
+            }
+            Phase::Dark if elapsed >= self.duration => {
+                self.blanked += elapsed;
+                self.background = self.dark.iter().map(Aggregate::mean).collect();
+                self.phase = Phase::Lit;
+                self.phase_start = Instant::now();
+                Some(Transition::Relight)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_dark(&self) -> bool {
+        self.enabled && self.phase == Phase::Dark
+    }
+
+    /// Returns whether frames received now belong entirely to the current phase.
+    pub fn is_settled(&self) -> bool {
+        !self.enabled || self.phase_start.elapsed() >= self.settle
+    }
+
+    /// Records a frame taken while the LEDs were blanked.
+    pub fn record_dark(&mut self, well: usize, value: f32) {
+        if self.dark.len() <= well {
+            self.dark.resize(well + 1, Aggregate::default());
+        }
+
+        self.dark[well].push(value);
+    }
+
+    /// Returns the mean reading of the well at index `well` over the last dark window.
+    pub fn background(&self, well: usize) -> Option<f32> {
+        self.background.get(well).copied().flatten()
+    }
+
+    /// Returns the total time the LEDs have been blanked since the run started.
+    pub fn blanked(&self) -> Duration {
+        if self.is_dark() {
+            self.blanked + self.phase_start.elapsed()
+        } else {
+            self.blanked
+        }
+    }
+
+    /// Returns the fraction of `elapsed` during which the LEDs were actually lit.
+    pub fn lit_fraction(&self, elapsed: Duration) -> f64 {
+        if elapsed.is_zero() {
+            return 1.0;
+        }
+
+        1.0 - (self.blanked().as_secs_f64() / elapsed.as_secs_f64()).min(1.0)
+    }
+}
@@ -1,38 +1,126 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+pub mod autorange;
+pub mod calibration;
 mod colors;
-mod microplate;
+pub mod density;
+pub mod design;
+pub mod geometry;
+pub mod groups;
+pub mod growth;
+pub mod hardware;
+pub mod heatmap;
+pub mod history;
+pub mod interleave;
+pub mod ltr303;
+pub mod metadata;
+pub mod plots;
+pub mod protocol;
+pub mod microplate;
 mod microwell;
+pub mod results;
+pub mod sample;
+pub mod sensors;
+pub mod sampling;
+pub mod selection;
+pub mod sequence;
+pub mod sequence_toml;
+pub mod stats;
+pub mod step_table;
+pub mod timeseries;
+pub mod viewer;
 mod serial;
 pub mod wavelength;
 pub mod config;
 
+use self::autorange::{AutoRanger, FrameStatus};
+use self::calibration::{Calibration, CalibrationAction, CalibrationWindow};
 use self::colors::*;
+use self::density::{Blanks, Quantities};
+use self::design::{DesignAction, DesignWizard};
+use self::geometry::PlateGeometry;
+use self::sample::{SampleRole, Samples};
+use self::selection::Selection;
+use self::sensors::{SensorSettings, SensorWindow};
+use self::hardware::HardwareMapping;
+use self::heatmap::Heatmap;
+use self::history::{Edit, History};
+use self::interleave::{DarkInterleave, Transition};
+use self::metadata::{RunMetadata, StopReason};
+use self::plots::PlotWindow;
+use self::results::ResultsWriter;
 use self::microplate::MicroPlate;
+use self::microwell::MicroWell;
+use self::sampling::Sampler;
+use self::viewer::ResultsViewer;
+use self::sequence::{SequenceFile, SequenceInfo};
 use self::serial::Serial;
+use self::wavelength::Wavelength;
 use chrono::prelude::*;
 use eframe::egui;
 use eframe::epaint::Color32;
-use std::fs::{OpenOptions};
-use std::io::Write;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
-use crate::config::{get_results_directory, load_sequence, save_sequence_as, set_results_directory};
+use crate::config::{default_results_directory, get_results_directory, load_sequence, save_sequence_as, set_results_directory};
 
 pub struct Application {
     sequence: Vec<MicroPlate>,
+    sequence_info: SequenceInfo,
+    samples: Samples,
     current_plate: usize,
+    history: History,
+    /// Name, author and samples as they were when the sequence was last saved or opened.
+    saved_info: SequenceInfo,
+    saved_samples: Samples,
+    /// Settings of the light sensors, sent to the board whenever they change.
+    sensors: SensorSettings,
+    saved_sensors: SensorSettings,
+    sensor_window: SensorWindow,
+    auto_range: AutoRanger,
+    /// Calibration of the board on the selected port, applied to every frame.
+    calibration: Option<Calibration>,
+    /// Board the calibration was looked up for, see [`calibration::device_key`].
+    calibration_device: Option<String>,
+    calibration_window: CalibrationWindow,
+
+    /// Wells copied with "Copy Wells", pasted onto other steps by label.
+    copied_wells: Option<Vec<MicroWell>>,
+    /// Step being dragged to another position of the step list.
+    dragged_step: Option<usize>,
+
+    /// Wells edited together from the side panel.
+    selection: Selection,
+    /// Replicate group typed in the side panel, assigned to the selection on request.
+    group: String,
 
     serial: Serial,
+    hardware: HardwareMapping,
 
     folder_path: PathBuf,
     current_file: PathBuf,
+    results_writer: Option<ResultsWriter>,
+    warnings: Vec<String>,
 
     sequence_file: Option<PathBuf>,
 
+    design_wizard: DesignWizard,
+
+    operator: String,
+    notes: String,
+    run_metadata: Option<RunMetadata>,
+
+    /// Readings of the current or last run, shown in `live_plot`.
+    quantities: Quantities,
+    /// Whether the results of the current run have transmittance and OD columns.
+    records_density: bool,
+    live_plot: PlotWindow,
+    heatmap: Heatmap,
+    results_viewer: ResultsViewer,
+
     sim_start: Instant,
     sequence_start: Instant,
-    last_write_time: Instant,
+    sampler: Sampler,
+    interleave: DarkInterleave,
     is_simulating: bool,
 }
 
@@ -47,14 +135,50 @@ impl Application {
 
         Self {
             sequence: vec![MicroPlate::default()],
+            sequence_info: SequenceInfo::default(),
+            samples: Samples::new(),
             current_plate: 0,
+            history: History::default(),
+            saved_info: SequenceInfo::default(),
+            saved_samples: Samples::new(),
+            sensors: SensorSettings::default(),
+            saved_sensors: SensorSettings::default(),
+            sensor_window: SensorWindow::default(),
+            auto_range: AutoRanger::default(),
+            calibration: None,
+            calibration_device: None,
+            calibration_window: CalibrationWindow::default(),
+            copied_wells: None,
+            dragged_step: None,
+            selection: Selection::default(),
+            group: String::new(),
             serial: Serial::default(),
-            folder_path: get_results_directory(),
+            hardware: HardwareMapping::default(),
+            folder_path: get_results_directory().unwrap_or_else(|e| {
+                let fallback = default_results_directory().unwrap_or_else(|_| PathBuf::from("."));
+                show_error(
+                    "Results Directory",
+                    format!("{}\nResults will be written to {} instead.", e, fallback.display()),
+                );
+                fallback
+            }),
             current_file: PathBuf::default(),
+            results_writer: None,
+            warnings: vec![],
             sequence_file: None,
+            design_wizard: DesignWizard::default(),
+            operator: String::new(),
+            notes: String::new(),
+            run_metadata: None,
+            quantities: Quantities::default(),
+            records_density: false,
+            live_plot: PlotWindow::default(),
+            heatmap: Heatmap::default(),
+            results_viewer: ResultsViewer::default(),
             sim_start: Instant::now(),
             sequence_start: Instant::now(),
-            last_write_time: Instant::now(),
+            sampler: Sampler::default(),
+            interleave: DarkInterleave::default(),
             is_simulating: false,
         }
     }
@@ -67,125 +191,637 @@ impl Application {
         let mut req = vec![0b11111111];
         req.push((255.0 + (-255.0 * self.sequence[self.current_plate].brightness / 100.0)) as u8);
 
-        for (idx, well) in self.sequence[self.current_plate].wells.iter().enumerate() {
+        let plate = &self.sequence[self.current_plate];
+        for (idx, well) in plate.wells.iter().enumerate() {
             if !well.led_on || well.disabled {
                 continue;
             }
 
-            req.push(
-                0b10000000
-                    | match idx {
-                        0 =>  (15 * 4)  + well.wavelength.to_u8(),
-                        2 =>  (14 * 4)  + well.wavelength.to_u8(),
-                        3 =>  (3 * 4)   + well.wavelength.to_u8(),
-                        4 =>  (2 * 4)   + well.wavelength.to_u8(),
-                        6 =>  (12 * 4)  + well.wavelength.to_u8(),
-                        10 => (11 * 4)  + well.wavelength.to_u8(),
-                        12 => (13 * 4)  + well.wavelength.to_u8(),
-                        13 => (0 * 4)   + well.wavelength.to_u8(),
-                        14 => (1 * 4)   + well.wavelength.to_u8(),
-                        17 => (10 * 4)  + well.wavelength.to_u8(),
-                        18 => (7 * 4)   + well.wavelength.to_u8(),
-                        19 => (6 * 4)   + well.wavelength.to_u8(),
-                        20 => (8 * 4)   + well.wavelength.to_u8(),
-                        22 => (9 * 4)   + well.wavelength.to_u8(),
-                        23 => (4 * 4)   + well.wavelength.to_u8(),
-                        24 => (5 * 4)   + well.wavelength.to_u8(),
-                        _ => 0b00000000,
-                    },
-            );
+            if let Some(channel) = self.hardware.channel_for_well(plate, idx) {
+                req.push(0b10000000 | (channel.led + well.wavelength.to_u8()));
+            }
         }
 
         self.serial.request_led(req.as_slice());
     }
+
+    /// Returns whether every step is laid out as the wired plate, telling the user otherwise.
+    fn check_wiring(&self) -> bool {
+        let unwired = self
+            .sequence
+            .iter()
+            .enumerate()
+            .find(|(_, step)| !self.hardware.is_wired_for(&step.geometry));
+
+        match unwired {
+            Some((idx, step)) => {
+                show_error(
+                    "Run Simulation",
+                    format!(
+                        "{} is laid out as {} but the board is wired as {}.",
+                        step.display_name(idx),
+                        step.geometry,
+                        self.hardware.geometry
+                    ),
+                );
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Returns the layout of the plate the sequence runs on.
+    fn geometry(&self) -> PlateGeometry {
+        self.sequence[self.current_plate].geometry.clone()
+    }
+
+    /// Shows the bulk actions of the selected wells and the sample of a single selected well.
+    fn show_selection(&mut self, ui: &mut egui::Ui) {
+        let plate = &mut self.sequence[self.current_plate];
+        let selected: Vec<usize> = plate
+            .wells
+            .iter()
+            .enumerate()
+            .filter(|(_, well)| !well.disabled && self.selection.contains(&well.label))
+            .map(|(idx, _)| idx)
+            .collect();
+
+        ui.horizontal(|ui| {
+            if ui.button("LED On").clicked() {
+                selected.iter().for_each(|idx| plate.wells[*idx].led_on = true);
+            }
+            if ui.button("LED Off").clicked() {
+                selected.iter().for_each(|idx| plate.wells[*idx].led_on = false);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Wavelength: ");
+            egui::ComboBox::from_id_source("selection_wavelength")
+                .selected_text("Set")
+                .show_ui(ui, |ui| {
+                    for wavelength in Wavelength::ALL {
+                        if ui.selectable_label(false, wavelength.to_string()).clicked() {
+                            selected.iter().for_each(|idx| plate.wells[*idx].wavelength = wavelength.clone());
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Brightness: ");
+            ui.add(egui::Slider::new(&mut plate.brightness, 0.0..=100.0).suffix("%"))
+                .on_hover_text("The board dims every LED together, so brightness applies to the whole step.");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Replicate Group: ");
+            ui.text_edit_singleline(&mut self.group);
+            if ui.button("Assign").clicked() {
+                for label in self.selection.labels() {
+                    let sample = self.samples.entry(label.clone()).or_default();
+                    sample.replicate_group = self.group.clone();
+                    if sample.is_empty() {
+                        self.samples.remove(label);
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Role: ");
+            egui::ComboBox::from_id_source("selection_role")
+                .selected_text("Set")
+                .show_ui(ui, |ui| {
+                    for role in SampleRole::ALL {
+                        if ui.selectable_label(false, role.to_string()).clicked() {
+                            for label in self.selection.labels() {
+                                let sample = self.samples.entry(label.clone()).or_default();
+                                sample.role = role;
+                                if sample.is_empty() {
+                                    self.samples.remove(label);
+                                }
+                            }
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Blank wells hold medium only. Transmittance and OD are relative to them.");
+        });
+
+        if let Some(label) = self.selection.single().cloned() {
+            ui.separator();
+            let mut sample = self.samples.get(&label).cloned().unwrap_or_default();
+            sample.show(ui);
+            if sample.is_empty() {
+                self.samples.remove(&label);
+            } else {
+                self.samples.insert(label, sample);
+            }
+        }
+    }
+
+    /// Returns the samples with the groups and roles of the wells placed by a design.
+    fn assign_treatments(&self, treatments: &Samples, replace: bool) -> Samples {
+        let mut samples = self.samples.clone();
+        if replace {
+            for (label, sample) in samples.iter_mut() {
+                if !treatments.contains_key(label) {
+                    sample.replicate_group.clear();
+                    sample.role = SampleRole::default();
+                }
+            }
+        }
+
+        for (label, treatment) in treatments {
+            let sample = samples.entry(label.clone()).or_default();
+            sample.replicate_group = treatment.replicate_group.clone();
+            sample.role = treatment.role;
+        }
+        samples.retain(|_, sample| !sample.is_empty());
+        samples
+    }
+
+    /// Inserts `step` at `index` of the sequence and shows it.
+    fn insert_step(&mut self, index: usize, step: MicroPlate) {
+        self.current_plate = self.history.execute(
+            Edit::Insert { index, steps: vec![step] },
+            &mut self.sequence,
+            &mut self.samples,
+        );
+    }
+
+    /// Shows the steps of the sequence, which can be reordered by dragging them.
+    fn show_step_list(&mut self, ui: &mut egui::Ui) {
+        let mut rects = vec![];
+
+        for (idx, plate) in self.sequence.iter().enumerate() {
+            let response = ui.horizontal(|ui| {
+                let color = plate.color.map(|[r, g, b]| Color32::from_rgb(r, g, b)).unwrap_or(Color32::TRANSPARENT);
+                let (swatch, _) = ui.allocate_exact_size(egui::vec2(6.0, 60.0), egui::Sense::hover());
+                ui.painter().rect_filled(swatch, 2.0, color);
+
+                ui.add(egui::SelectableLabel::new(
+                    self.current_plate == idx,
+                    format!("{}\nsequence duration: {}s\nwavelength: {}\nbrightness: {}%", plate.display_name(idx), plate.duration, plate.wavelength, plate.brightness),
+                ))
+                .interact(egui::Sense::drag())
+            }).inner;
+
+            if response.clicked() {
+                self.current_plate = idx;
+            }
+            if response.drag_started() {
+                self.dragged_step = Some(idx);
+            }
+            rects.push(response.rect);
+        }
+
+        let Some(from) = self.dragged_step else {
+            return;
+        };
+
+        let pointer = ui.input(|i| i.pointer.interact_pos());
+        let target = pointer.and_then(|pointer| {
+            rects.iter().position(|rect| pointer.y >= rect.top() && pointer.y <= rect.bottom())
+        });
+
+        if let Some(to) = target {
+            // Mark where the dragged step will be dropped
+            let y = if to > from { rects[to].bottom() } else { rects[to].top() };
+            ui.painter().hline(rects[to].x_range(), y, egui::Stroke::new(2.0, COLOR_SLATE_100));
+        }
+
+        if !ui.input(|i| i.pointer.any_down()) {
+            self.dragged_step = None;
+            if let Some(to) = target.filter(|to| *to != from) {
+                self.current_plate = self.history.execute(Edit::Move { from, to }, &mut self.sequence, &mut self.samples);
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(current) = self.history.undo(&mut self.sequence, &mut self.samples) {
+            self.current_plate = current;
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(current) = self.history.redo(&mut self.sequence, &mut self.samples) {
+            self.current_plate = current;
+        }
+    }
+
+    /// Returns whether the sequence changed since it was last saved or opened.
+    fn is_dirty(&self) -> bool {
+        self.history.is_dirty()
+            || self.sequence_info.name != self.saved_info.name
+            || self.sequence_info.author != self.saved_info.author
+            || self.samples != self.saved_samples
+            || self.sensors != self.saved_sensors
+    }
+
+    fn mark_saved(&mut self) {
+        self.history.mark_saved();
+        self.saved_info = self.sequence_info.clone();
+        self.saved_samples = self.samples.clone();
+        self.saved_sensors = self.sensors.clone();
+    }
+
+    /// Saves the sequence, asking for a file when `pick` is set, and returns whether it was saved.
+    fn save_sequence(&mut self, title: &str, pick: bool) -> bool {
+        let path = match &self.sequence_file {
+            Some(path) if !pick => path.clone(),
+            _ => match rfd::FileDialog::new()
+                .set_file_name("file_name.json")
+                .add_filter("JSON", &["json"])
+                .add_filter("TOML", &["toml"])
+                .save_file() {
+                Some(path) => path,
+                None => return false,
+            },
+        };
+
+        match save_sequence_as(path.clone(), &self.sequence_file_contents()) {
+            Ok(()) => {
+                self.sequence_file = Some(path);
+                self.mark_saved();
+                true
+            }
+            Err(e) => {
+                show_error(title, e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Offers to save unsaved changes before they are discarded. Returns whether to go on.
+    fn confirm_discard(&mut self, title: &str) -> bool {
+        if !self.is_dirty() {
+            return true;
+        }
+
+        let answer = rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title(title)
+            .set_description("The sequence has unsaved changes. Save them first?")
+            .set_buttons(rfd::MessageButtons::YesNoCancel)
+            .show();
+
+        match answer {
+            rfd::MessageDialogResult::Yes => self.save_sequence(title, false),
+            rfd::MessageDialogResult::No => true,
+            _ => false,
+        }
+    }
+
+    /// Stamps the sequence with its modification time and wraps it for saving.
+    fn sequence_file_contents(&mut self) -> SequenceFile {
+        let now = Local::now();
+        self.sequence_info.created.get_or_insert(now);
+        self.sequence_info.modified = Some(now);
+
+        SequenceFile {
+            samples: self.samples.clone(),
+            sensors: self.sensors.clone(),
+            ..SequenceFile::new(self.sequence_info.clone(), self.sequence.clone())
+        }
+    }
+
+    fn stop_simulation(&mut self, reason: StopReason) {
+        self.clear_leds();
+        self.is_simulating = false;
+        self.quantities.finish(self.sim_start.elapsed().as_secs_f64());
+
+        if let Some(writer) = &mut self.results_writer {
+            writer.finish();
+            self.warnings.extend(writer.take_warnings());
+            // Keep the sidecar next to the rows when they had to be spooled elsewhere
+            self.current_file = writer.path().to_path_buf();
+        }
+
+        if let Some(metadata) = &mut self.run_metadata {
+            metadata.stop(reason);
+            metadata.set_dark_readings(&self.interleave);
+            if let Err(e) = metadata.save(&self.current_file) {
+                self.warnings.push(format!("Unable to write run metadata: {}", e));
+            }
+        }
+    }
+
+    fn write_run_metadata(&mut self) {
+        let mut metadata = RunMetadata {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            results_file: self
+                .current_file
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            operator: self.operator.clone(),
+            notes: self.notes.clone(),
+            serial_port: None,
+            device: None,
+            hardware: self.hardware.clone(),
+            sampling_interval_s: self.sampler.interval.as_secs_f64(),
+            dark_readings: None,
+            started: Local::now(),
+            stopped: None,
+            stop_reason: None,
+            sequence_info: self.sequence_info.clone(),
+            sequence: self.sequence.clone(),
+            samples: self.samples.clone(),
+            sensors: self.sensors.clone(),
+            calibration: self.calibration.clone(),
+        };
+        metadata.set_port(self.serial.selected_port());
+        metadata.set_dark_readings(&self.interleave);
+        if let Err(e) = metadata.save(&self.current_file) {
+            self.warnings.push(format!("Unable to write run metadata: {}", e));
+        }
+
+        self.run_metadata = Some(metadata);
+    }
+
+    fn write_results_row(&mut self) {
+        let plate = &self.sequence[self.current_plate];
+        let elapsed = self.sim_start.elapsed().as_secs_f64();
+        // Plot the background-corrected reading when dark readings are taken
+        let readings: Vec<(usize, f64)> = plate
+            .usable_wells()
+            .into_iter()
+            .filter_map(|idx| results::reading(idx, &self.sampler, &self.interleave).map(|value| (idx, value as f64)))
+            .collect();
+        let blanks = Blanks::new(plate, &self.samples, &readings);
+
+        let row = results::format_row(
+            Local::now(),
+            self.sim_start.elapsed(),
+            self.current_plate,
+            plate,
+            &self.sampler,
+            &self.interleave,
+            self.records_density.then_some(&blanks),
+        );
+        if let Some(writer) = &mut self.results_writer {
+            writer.write_row(row);
+        }
+
+        self.quantities.record(elapsed, plate, &blanks, &readings);
+
+        // Start collecting the next row
+        self.sampler.reset();
+    }
+}
+
+/// Shows a blocking error dialog.
+pub(crate) fn show_error(title: &str, description: String) {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title(title)
+        .set_description(description)
+        .show();
 }
 
 impl eframe::App for Application {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.serial
-            .request_data(&mut self.sequence[self.current_plate]);
+        let device = self.serial.selected_port().map(calibration::device_key);
+        if device != self.calibration_device {
+            self.calibration = match device.as_deref().map(Calibration::load_for) {
+                Some(Ok(calibration)) => calibration,
+                Some(Err(e)) => {
+                    self.warnings.push(format!("Unable to load the sensor calibration: {}", e));
+                    None
+                }
+                None => None,
+            };
+            self.calibration_device = device;
+        }
+
+        let (sensors, auto_range) = (&self.sensors, &self.auto_range);
+        let readings = self.serial.request_data(
+            &mut self.sequence[self.current_plate],
+            &self.hardware,
+            |channel| auto_range.config_for(channel, sensors),
+        );
+        for reading in readings {
+            self.calibration_window.record(reading.sensor, reading.value);
+            let value = match &self.calibration {
+                Some(calibration) => calibration.apply(reading.sensor, reading.value as f64) as f32,
+                None => reading.value,
+            };
+            self.sequence[self.current_plate].wells[reading.well].measurement = value;
+
+            if !self.is_simulating || !self.interleave.is_settled() {
+                // Frames straddling a blanking transition mix lit and dark light
+                continue;
+            }
+
+            if self.interleave.is_dark() {
+                self.interleave.record_dark(reading.well, value);
+                continue;
+            }
+
+            let Some(raw) = reading.raw else {
+                self.sampler.record(reading.well, value);
+                self.sampler.record_uncalibrated(reading.well, reading.value);
+                continue;
+            };
+            let Some(channel) = self.hardware.channel(reading.sensor) else {
+                continue;
+            };
+            let check = self.auto_range.check(channel, &self.sensors, raw, reading.gain, Instant::now());
+            if let Some(config) = check.change {
+                self.serial.configure_sensor(reading.sensor, &config);
+                if check.status != FrameStatus::Settling {
+                    self.sampler.record_range_change(reading.well, check.gain, config.gain);
+                }
+            }
+
+            match check.status {
+                FrameStatus::Settling => continue,
+                FrameStatus::Saturated => {
+                    self.sampler.record_saturated(reading.well);
+                    // Only keep the clipped frame when the gain cannot be lowered any further
+                    if check.change.is_some() {
+                        continue;
+                    }
+                }
+                FrameStatus::InRange => {}
+            }
+            self.sampler.record(reading.well, value);
+            self.sampler.record_uncalibrated(reading.well, reading.value);
+            self.sampler.record_raw(reading.well, raw, check.gain);
+        }
+        self.calibration_window.update();
+
+        if self.is_simulating {
+            match self.interleave.update() {
+                Some(Transition::Blank) => self.clear_leds(),
+                Some(Transition::Relight) => self.request_leds(),
+                None => {}
+            }
+        }
 
         if self.is_simulating
             && self.sequence_start.elapsed()
                 >= Duration::from_secs(self.sequence[self.current_plate].duration)
         {
             if self.current_plate + 1 >= self.sequence.len() {
-                self.is_simulating = false;
-                self.clear_leds();
+                self.stop_simulation(StopReason::Completed);
             } else {
                 self.current_plate += 1;
                 self.sequence_start = Instant::now();
-                self.request_leds();
+                self.quantities.start_step(
+                    self.sim_start.elapsed().as_secs_f64(),
+                    self.current_plate,
+                    &self.sequence[self.current_plate],
+                );
+                if !self.interleave.is_dark() {
+                    self.request_leds();
+                }
             }
         }
 
-        if self.is_simulating && self.last_write_time.elapsed() >= Duration::from_secs(1) {
-            // Open file in append mode, or create it if it doesn't exist
-            let mut file = OpenOptions::new()
-                .write(true)
-                .append(true)
-                .create(true)
-                .open(&self.current_file)
-                .expect("Unable to open file.");
-
-            write!(
-                file,
-                "{},{},{},",
-                self.sequence[self.current_plate].brightness,
-                self.sequence[self.current_plate].wavelength,
-                self.sequence[self.current_plate].duration
-            )
-            .expect("Unable to write brightness and wavelength.");
-            writeln!(
-                file,
-                "{}",
-                self.sequence[self.current_plate]
-                    .wells
-                    .iter()
-                    .filter(|well| !well.disabled)
-                    .map(|well| well.measurement.to_string())
-                    .collect::<Vec<String>>()
-                    .join(",")
-            )
-            .expect("Unable to write line of well data");
-
-            // Update the last write time
-            self.last_write_time = Instant::now();
+        if self.is_simulating && self.sampler.is_due() {
+            self.write_results_row();
+        }
+
+        if let Some(writer) = &mut self.results_writer {
+            writer.update();
+            self.warnings.extend(writer.take_warnings());
         }
 
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Sequence Name: ");
+                        ui.text_edit_singleline(&mut self.sequence_info.name);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Author: ");
+                        ui.text_edit_singleline(&mut self.sequence_info.author);
+                    });
+                    ui.separator();
+
                     if ui.button("Save Sequence").clicked() {
-                        if let Some(path) = &self.sequence_file {
-                            save_sequence_as(path.clone(), self.sequence.clone());
-                        } else if let Some(path) = rfd::FileDialog::new()
-                            .set_file_name("file_name.json")
-                            .add_filter("JSON", &["json"])
-                            .save_file() {
-                            self.sequence_file = Some(path.clone());
+                        self.save_sequence("Save Sequence", false);
+                        ui.close_menu();
+                    }
 
-                            save_sequence_as(path, self.sequence.clone());
-                            ui.close_menu();
+                    if ui.button("Save Sequence As").clicked() {
+                        self.save_sequence("Save Sequence As", true);
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Open").clicked() {
+                        if self.confirm_discard("Open") {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Sequence", &["json", "toml"])
+                                .pick_file() {
+                                match load_sequence(path.clone()) {
+                                    Ok(sequence) => {
+                                        self.sequence = sequence.steps;
+                                        self.sequence_info = sequence.info;
+                                        self.samples = sequence.samples;
+                                        self.sensors = sequence.sensors;
+                                        self.auto_range.reset();
+                                        self.serial.configure_sensors(&self.sensors, &self.hardware);
+                                        self.current_plate = 0;
+                                        self.selection.clear();
+                                        self.sequence_file = Some(path);
+                                        self.history.clear();
+                                        self.mark_saved();
+                                    }
+                                    Err(e) => show_error("Open", e.to_string()),
+                                }
+                            }
                         }
+                        ui.close_menu();
                     }
 
-                    if ui.button("Save Sequence As").clicked() {
+                    if ui.button("Import Step Table").clicked() {
+                        if self.confirm_discard("Import Step Table") {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Step Table", &["csv", "xlsx", "xls", "ods"])
+                                .pick_file() {
+                                match step_table::import(&path, &self.geometry()) {
+                                    Ok(sequence) => {
+                                        // The table only holds steps, what was known about the
+                                        // previous sequence does not describe the imported one.
+                                        self.current_plate = self.history.execute(
+                                            Edit::Group(vec![
+                                                Edit::Replace { before: self.sequence.clone(), after: sequence },
+                                                Edit::Samples { before: self.samples.clone(), after: Samples::new() },
+                                            ]),
+                                            &mut self.sequence,
+                                            &mut self.samples,
+                                        );
+                                        self.sequence_info = SequenceInfo::default();
+                                        self.selection.clear();
+                                        self.sequence_file = None;
+                                    }
+                                    Err(e) => show_error(
+                                        "Import Step Table",
+                                        format!("Unable to import {}:\n{}", path.display(), e),
+                                    ),
+                                }
+                            }
+                        }
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Export Step Table").clicked() {
                         if let Some(path) = rfd::FileDialog::new()
-                            .set_file_name("file_name.json")
-                            .add_filter("JSON", &["json"])
+                            .set_file_name("steps.xlsx")
+                            .add_filter("Excel", &["xlsx"])
+                            .add_filter("CSV", &["csv"])
                             .save_file() {
-                            self.sequence_file = Some(path.clone());
+                            if let Err(e) = step_table::export(&path, &self.sequence) {
+                                show_error(
+                                    "Export Step Table",
+                                    format!("Unable to export {}:\n{}", path.display(), e),
+                                );
+                            }
+                            ui.close_menu();
+                        }
+                    }
 
-                            save_sequence_as(path, self.sequence.clone());
+                    if ui.button("Open Results").clicked() {
+                        if let Some(paths) = rfd::FileDialog::new()
+                            .add_filter("Results", &["csv"])
+                            .set_directory(&self.folder_path)
+                            .pick_files() {
+                            for path in paths {
+                                if let Err(e) = self.results_viewer.add_run(&path) {
+                                    show_error(
+                                        "Open Results",
+                                        format!("Unable to open {}:\n{}", path.display(), e),
+                                    );
+                                }
+                            }
                             ui.close_menu();
                         }
                     }
 
-                    if ui.button("Open").clicked() {
-                        if let Some(path) = rfd::FileDialog::new().pick_file() {
-                            self.sequence = load_sequence(path.clone());
-                            self.sequence_file = Some(path);
+                    if ui.button("Convert Legacy Results").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("CSV", &["csv"])
+                            .set_directory(&self.folder_path)
+                            .pick_file() {
+                            match results::convert_legacy_file(&path) {
+                                Ok(converted) => {
+                                    rfd::MessageDialog::new()
+                                        .set_level(rfd::MessageLevel::Info)
+                                        .set_title("Convert Legacy Results")
+                                        .set_description(format!("Converted results written to {}", converted.display()))
+                                        .show();
+                                }
+                                Err(e) => show_error(
+                                    "Convert Legacy Results",
+                                    format!("Unable to convert {}: {}", path.display(), e),
+                                ),
+                            }
                             ui.close_menu();
                         }
                     }
@@ -196,25 +832,146 @@ impl eframe::App for Application {
                     ).clicked() {
                         if let Some(path) = rfd::FileDialog::new().pick_folder() {
                             self.folder_path = path;
-                            set_results_directory(self.folder_path.clone());
+                            if let Err(e) = set_results_directory(self.folder_path.clone()) {
+                                show_error("Results Directory", e.to_string());
+                            }
                             ui.close_menu();
                         }
                     }
                 });
 
+                ui.menu_button("Edit", |ui| {
+                    ui.add_enabled_ui(!self.is_simulating, |ui| {
+                        if ui
+                            .add_enabled(self.history.can_undo(), egui::Button::new("Undo").shortcut_text("Ctrl+Z"))
+                            .clicked()
+                        {
+                            self.undo();
+                            ui.close_menu();
+                        }
+                        if ui
+                            .add_enabled(self.history.can_redo(), egui::Button::new("Redo").shortcut_text("Ctrl+Shift+Z"))
+                            .clicked()
+                        {
+                            self.redo();
+                            ui.close_menu();
+                        }
+                    });
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.live_plot.open, "Live Plot");
+                    ui.separator();
+                    self.heatmap.show_settings(ui);
+                });
+
                 ui.menu_button("Tools", |ui| {
                     self.serial.show(ctx, ui);
+
+                    ui.add_enabled_ui(!self.is_simulating, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Operator: ");
+                            ui.text_edit_singleline(&mut self.operator);
+                        });
+                        ui.label("Notes: ");
+                        ui.text_edit_multiline(&mut self.notes);
+                    });
+
+                    ui.add_enabled_ui(!self.is_simulating, |ui| {
+                        let current = self.geometry();
+                        ui.menu_button(format!("Plate Format: {}", current), |ui| {
+                            for geometry in PlateGeometry::presets() {
+                                let selected = geometry == current;
+                                if ui.selectable_label(selected, geometry.to_string()).clicked() {
+                                    if !selected {
+                                        let mut after = self.sequence.clone();
+                                        for plate in after.iter_mut() {
+                                            plate.set_geometry(geometry.clone());
+                                        }
+                                        self.history.execute(
+                                            Edit::Replace { before: self.sequence.clone(), after },
+                                            &mut self.sequence,
+                                            &mut self.samples,
+                                        );
+                                        self.selection.clear();
+                                    }
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+
+                    ui.add_enabled_ui(!self.is_simulating, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Sampling Interval: ");
+                            let mut interval = self.sampler.interval.as_secs_f64();
+                            if ui.add(
+                                egui::DragValue::new(&mut interval)
+                                    .speed(0.1)
+                                    .clamp_range(0.1..=3600.0)
+                                    .suffix(" s")
+                            ).changed() {
+                                self.sampler.interval = Duration::from_secs_f64(interval);
+                            }
+                        });
+                    });
+
+                    ui.add_enabled_ui(!self.is_simulating, |ui| {
+                        ui.checkbox(&mut self.interleave.enabled, "Dark Readings");
+                        ui.add_enabled_ui(self.interleave.enabled, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Every: ");
+                                let mut interval = self.interleave.interval.as_secs_f64();
+                                if ui.add(
+                                    egui::DragValue::new(&mut interval)
+                                        .speed(1.0)
+                                        .clamp_range(1.0..=86400.0)
+                                        .suffix(" s")
+                                ).changed() {
+                                    self.interleave.interval = Duration::from_secs_f64(interval);
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Blank For: ");
+                                let mut duration = self.interleave.duration.as_secs_f64();
+                                if ui.add(
+                                    egui::DragValue::new(&mut duration)
+                                        .speed(0.1)
+                                        .clamp_range(0.5..=600.0)
+                                        .suffix(" s")
+                                ).changed() {
+                                    self.interleave.duration = Duration::from_secs_f64(duration);
+                                }
+                            });
+                        });
+                    });
+
+                    if ui.button("Sensor Settings").clicked() {
+                        self.sensor_window.open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Sensor Calibration").clicked() {
+                        self.calibration_window.open = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("Design Wizard").clicked() {
+                        self.design_wizard.open = true;
+                        ui.close_menu();
+                    }
                 });
 
                 if self.is_simulating {
                     if ui.button("Stop Simulation").clicked() {
-                        self.clear_leds();
-                        self.is_simulating = false;
+                        self.stop_simulation(StopReason::Stopped);
                     }
-                } else if ui.button("Run Simulation").clicked() {
+                } else if ui.button("Run Simulation").clicked() && self.check_wiring() {
                     self.is_simulating = true;
                     self.current_plate = 0;
 
+                    self.auto_range.reset();
+                    self.serial.configure_sensors(&self.sensors, &self.hardware);
                     self.request_leds();
                     self.sequence[0].clear();
 
@@ -229,33 +986,23 @@ impl eframe::App for Application {
                     self.current_file
                         .push(format!("microfluidic_test_{}.csv", time_str));
 
-                    self.last_write_time = Instant::now();
+                    self.sampler.reset();
                     self.sequence_start = Instant::now();
                     self.sim_start = Instant::now();
 
-                    let mut file = OpenOptions::new()
-                        .write(true)
-                        .append(true)
-                        .create(true)
-                        .open(&self.current_file)
-                        .expect("Unable to open file.");
-                    write!(
-                        file,
-                        "Brightness [%],Wavelength [nm],Duration [s],"
-                    )
-                        .expect("Unable to write file header.");
-                    writeln!(
-                        file,
-                        "{}",
-                        self.sequence[self.current_plate]
-                            .wells
-                            .iter()
-                            .filter(|well| !well.disabled)
-                            .map(|well| well.label.to_string())
-                            .collect::<Vec<String>>()
-                            .join(",")
-                    )
-                        .expect("Unable to write file header.");
+                    self.interleave.start();
+                    self.quantities.clear();
+                    self.quantities.start_step(0.0, 0, &self.sequence[0]);
+                    self.records_density = density::has_blanks(&self.samples);
+                    self.results_writer = Some(ResultsWriter::new(
+                        self.current_file.clone(),
+                        results::format_header(
+                            &self.sequence[self.current_plate],
+                            &self.interleave,
+                            self.records_density,
+                        ),
+                    ));
+                    self.write_run_metadata();
                 }
 
                 if self.is_simulating {
@@ -264,41 +1011,168 @@ impl eframe::App for Application {
                         self.sequence.iter().map(|plate| plate.duration).sum();
 
                     ui.label(format!("{} seconds left", total_duration - sim_duration));
+
+                    if self.interleave.enabled {
+                        ui.label(format!(
+                            "{:.1}% of dose delivered",
+                            self.interleave.lit_fraction(self.sim_start.elapsed()) * 100.0
+                        ));
+                    }
                 }
             });
         });
 
+        if !self.is_simulating {
+            let geometry = self.geometry();
+            match self.design_wizard.show(ctx, &geometry) {
+                Some(DesignAction::Replace(design)) => {
+                    let samples = self.assign_treatments(&design.samples, true);
+                    self.current_plate = self.history.execute(
+                        Edit::Group(vec![
+                            Edit::Samples { before: self.samples.clone(), after: samples },
+                            Edit::Replace { before: self.sequence.clone(), after: design.steps },
+                        ]),
+                        &mut self.sequence,
+                        &mut self.samples,
+                    );
+                }
+                Some(DesignAction::Append(design)) => {
+                    let samples = self.assign_treatments(&design.samples, false);
+                    self.current_plate = self.history.execute(
+                        Edit::Group(vec![
+                            Edit::Samples { before: self.samples.clone(), after: samples },
+                            Edit::Insert { index: self.sequence.len(), steps: design.steps },
+                        ]),
+                        &mut self.sequence,
+                        &mut self.samples,
+                    );
+                }
+                None => {}
+            }
+        }
+
+        if self.sensor_window.show(ctx, &mut self.sensors, &self.hardware, !self.is_simulating) {
+            self.auto_range.reset();
+            self.serial.configure_sensors(&self.sensors, &self.hardware);
+        }
+
+        match self.calibration_window.show(
+            ctx,
+            self.calibration_device.as_deref(),
+            self.calibration.as_ref(),
+            &self.hardware,
+            !self.is_simulating,
+        ) {
+            Some(CalibrationAction::Save(calibration)) => {
+                match Calibration::path_for(&calibration.device).and_then(|path| calibration.save(&path)) {
+                    Ok(()) => self.calibration = Some(calibration),
+                    Err(e) => show_error("Sensor Calibration", e.to_string()),
+                }
+            }
+            Some(CalibrationAction::Remove) => {
+                let answer = rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Warning)
+                    .set_title("Sensor Calibration")
+                    .set_description("Remove the calibration of this board? Its sensors will no longer be corrected.")
+                    .set_buttons(rfd::MessageButtons::YesNo)
+                    .show();
+                if let (rfd::MessageDialogResult::Yes, Some(device)) = (answer, &self.calibration_device) {
+                    match Calibration::remove_for(device) {
+                        Ok(()) => self.calibration = None,
+                        Err(e) => show_error("Sensor Calibration", e.to_string()),
+                    }
+                }
+            }
+            None => {}
+        }
+
+        self.live_plot.show(ctx, "Live Plot", &self.quantities, &self.samples);
+        self.results_viewer.show(ctx);
+
+        if !self.warnings.is_empty() {
+            egui::TopBottomPanel::bottom("warnings").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} warning(s)", self.warnings.len()));
+                    if ui.button("Dismiss").clicked() {
+                        self.warnings.clear();
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(80.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for warning in &self.warnings {
+                            ui.colored_label(ui.visuals().warn_fg_color, warning);
+                        }
+                    });
+            });
+        }
+
+        if !self.selection.is_empty() {
+            egui::SidePanel::right("selection").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    match self.selection.single() {
+                        Some(label) => ui.heading(format!("Well {}", label)),
+                        None => ui.heading(format!("{} Wells", self.selection.len())),
+                    };
+                    if ui.button("Clear Selection").clicked() {
+                        self.selection.clear();
+                    }
+                });
+                ui.separator();
+
+                ui.add_enabled_ui(!self.is_simulating, |ui| self.show_selection(ui));
+            });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.add_enabled_ui(!self.is_simulating, |ui| {
                 ui.horizontal(|ui| {
                     ui.vertical(|ui|{
-                        if ui.button("New Pattern").clicked() {
-                            self.sequence.push(MicroPlate::default());
-                            self.current_plate = self.sequence.len() - 1;
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("New Pattern").clicked() {
+                                self.insert_step(self.sequence.len(), MicroPlate::with_geometry(self.geometry()));
+                            }
+                            if ui.button("Duplicate").clicked() {
+                                self.insert_step(self.current_plate + 1, self.sequence[self.current_plate].clone());
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button("Insert Before").clicked() {
+                                self.insert_step(self.current_plate, MicroPlate::with_geometry(self.geometry()));
+                            }
+                            if ui.button("Insert After").clicked() {
+                                self.insert_step(self.current_plate + 1, MicroPlate::with_geometry(self.geometry()));
+                            }
+                        });
 
                         ui.add_space(5.0);
 
                         egui::ScrollArea::vertical()
                             .min_scrolled_height(325.0 + 80.0)
-                            .show(ui, |ui|{
-                                for (idx, plate) in self.sequence.iter().enumerate() {
-                                    ui.selectable_value(
-                                        &mut self.current_plate,
-                                        idx,
-                                        format!("Well Pattern {}\nsequence duration: {}s\nwavelength: {}\nbrightness: {}%", idx + 1, plate.duration, plate.wavelength, plate.brightness),
-                                    );
-                                }
-                            });
+                            .show(ui, |ui| self.show_step_list(ui));
                     });
 
                     ui.vertical(|ui| {
                         ui.horizontal(|ui| {
                             ui.add_enabled_ui(self.sequence.len() > 1, |ui| {
                                 if ui.button("Remove Pattern").clicked() {
-                                    self.sequence.remove(self.current_plate);
-                                    if self.current_plate != 0 {
-                                        self.current_plate -= 1;
+                                    let name = self.sequence[self.current_plate].display_name(self.current_plate);
+                                    let confirmed = rfd::MessageDialog::new()
+                                        .set_level(rfd::MessageLevel::Warning)
+                                        .set_title("Remove Pattern")
+                                        .set_description(format!("Remove \"{}\" from the sequence?", name))
+                                        .set_buttons(rfd::MessageButtons::YesNo)
+                                        .show();
+                                    if confirmed == rfd::MessageDialogResult::Yes {
+                                        self.current_plate = self.history.execute(
+                                            Edit::Remove {
+                                                index: self.current_plate,
+                                                step: Box::new(self.sequence[self.current_plate].clone()),
+                                            },
+                                            &mut self.sequence,
+                                            &mut self.samples,
+                                        );
                                     }
                                 }
                             });
@@ -309,14 +1183,63 @@ impl eframe::App for Application {
                             if ui.button("Select All").clicked() {
                                 self.sequence[self.current_plate].wells.iter_mut().for_each(|v| v.led_on = true);
                             }
+
+                            if ui.button("Copy Wells")
+                                .on_hover_text("Copies the selected wells, or every well when none is selected.")
+                                .clicked() {
+                                self.copied_wells = Some(self.sequence[self.current_plate].copy_wells(&self.selection));
+                            }
+
+                            if let Some(copied) = &self.copied_wells {
+                                if ui.button("Paste Wells").clicked() {
+                                    self.sequence[self.current_plate].paste_wells(copied);
+                                }
+                            }
+
+                            if let Some(spec) = &self.sequence[self.current_plate].design {
+                                if ui.button("Edit Design").clicked() {
+                                    self.design_wizard.load_spec(spec.clone());
+                                }
+                            }
                         });
 
-                        self.sequence[self.current_plate].show(ctx, ui);
+                        let shading = self.heatmap.shading(&self.quantities.readings, f64::INFINITY);
+                        self.sequence[self.current_plate].show(ctx, ui, &mut self.selection, &shading);
+                        ui.label("Ctrl-click, shift-click, drag or click a header to select wells.");
                     })
                 });
             });
         });
 
+        if !self.is_simulating {
+            // Text fields keep their own undo history while they have focus
+            let editing_text = ctx.memory(|memory| memory.focus().is_some());
+            let (undo, redo) = ctx.input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z),
+                    input.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z),
+                )
+            });
+            if undo && !editing_text {
+                self.undo();
+            } else if redo && !editing_text {
+                self.redo();
+            }
+
+            let interacting = editing_text || ctx.input(|input| input.pointer.any_down());
+            self.history.track(&self.sequence, &self.samples, self.current_plate, interacting);
+        }
+
         ctx.request_repaint();
     }
+
+    fn on_close_event(&mut self) -> bool {
+        self.confirm_discard("Quit")
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.is_simulating {
+            self.stop_simulation(StopReason::Closed);
+        }
+    }
 }
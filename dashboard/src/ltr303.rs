@@ -0,0 +1,269 @@
+use std::fmt;
+use std::fmt::Formatter;
+use serde::{Serialize, Deserialize};
+
+/// Gain older firmware computed the lux it sends with.
+pub const FIRMWARE_GAIN: f64 = 96.0;
+/// Integration factor older firmware divided the lux it sends by.
+pub const FIRMWARE_INTEGRATION_FACTOR: f64 = 1.0;
+
+/// Gain of the sensor, set in bits 4:2 of the `ALS_CONTR` register.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Gain {
+    X1,
+    X2,
+    X4,
+    X8,
+    X48,
+    #[default]
+    X96,
+}
+
+impl Gain {
+    pub const ALL: [Gain; 6] = [Gain::X1, Gain::X2, Gain::X4, Gain::X8, Gain::X48, Gain::X96];
+
+    pub fn factor(&self) -> f64 {
+        match self {
+            Gain::X1 => 1.0,
+            Gain::X2 => 2.0,
+            Gain::X4 => 4.0,
+            Gain::X8 => 8.0,
+            Gain::X48 => 48.0,
+            Gain::X96 => 96.0,
+        }
+    }
+
+    /// Returns the next lower gain, if any.
+    pub fn lower(&self) -> Option<Gain> {
+        let idx = Gain::ALL.iter().position(|gain| gain == self)?;
+        idx.checked_sub(1).map(|idx| Gain::ALL[idx])
+    }
+
+    /// Returns the next higher gain, if any.
+    pub fn higher(&self) -> Option<Gain> {
+        let idx = Gain::ALL.iter().position(|gain| gain == self)?;
+        Gain::ALL.get(idx + 1).copied()
+    }
+
+    /// Returns the gain set by `bits`, `None` for the reserved values.
+    pub fn from_bits(bits: u8) -> Option<Gain> {
+        Gain::ALL.into_iter().find(|gain| gain.bits() == bits)
+    }
+
+    fn bits(&self) -> u8 {
+        match self {
+            Gain::X1 => 0b000,
+            Gain::X2 => 0b001,
+            Gain::X4 => 0b010,
+            Gain::X8 => 0b011,
+            Gain::X48 => 0b110,
+            Gain::X96 => 0b111,
+        }
+    }
+}
+
+impl fmt::Display for Gain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "×{}", self.factor())
+    }
+}
+
+/// Time the sensor integrates light for, set in bits 5:3 of the `ALS_MEAS_RATE` register.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegrationTime {
+    #[default]
+    Ms50,
+    Ms100,
+    Ms150,
+    Ms200,
+    Ms250,
+    Ms300,
+    Ms350,
+    Ms400,
+}
+
+impl IntegrationTime {
+    pub const ALL: [IntegrationTime; 8] = [
+        IntegrationTime::Ms50,
+        IntegrationTime::Ms100,
+        IntegrationTime::Ms150,
+        IntegrationTime::Ms200,
+        IntegrationTime::Ms250,
+        IntegrationTime::Ms300,
+        IntegrationTime::Ms350,
+        IntegrationTime::Ms400,
+    ];
+
+    pub fn millis(&self) -> u32 {
+        match self {
+            IntegrationTime::Ms50 => 50,
+            IntegrationTime::Ms100 => 100,
+            IntegrationTime::Ms150 => 150,
+            IntegrationTime::Ms200 => 200,
+            IntegrationTime::Ms250 => 250,
+            IntegrationTime::Ms300 => 300,
+            IntegrationTime::Ms350 => 350,
+            IntegrationTime::Ms400 => 400,
+        }
+    }
+
+    /// Returns the integration factor of the lux formula.
+    pub fn factor(&self) -> f64 {
+        self.millis() as f64 / 50.0
+    }
+
+    fn bits(&self) -> u8 {
+        match self {
+            IntegrationTime::Ms100 => 0b000,
+            IntegrationTime::Ms50 => 0b001,
+            IntegrationTime::Ms200 => 0b010,
+            IntegrationTime::Ms400 => 0b011,
+            IntegrationTime::Ms150 => 0b100,
+            IntegrationTime::Ms250 => 0b101,
+            IntegrationTime::Ms300 => 0b110,
+            IntegrationTime::Ms350 => 0b111,
+        }
+    }
+}
+
+impl fmt::Display for IntegrationTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ms", self.millis())
+    }
+}
+
+/// Interval between two measurements of the sensor, set in bits 2:0 of the `ALS_MEAS_RATE`
+/// register.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MeasurementRate {
+    Ms50,
+    #[default]
+    Ms100,
+    Ms200,
+    Ms500,
+    Ms1000,
+    Ms2000,
+}
+
+impl MeasurementRate {
+    pub const ALL: [MeasurementRate; 6] = [
+        MeasurementRate::Ms50,
+        MeasurementRate::Ms100,
+        MeasurementRate::Ms200,
+        MeasurementRate::Ms500,
+        MeasurementRate::Ms1000,
+        MeasurementRate::Ms2000,
+    ];
+
+    pub fn millis(&self) -> u32 {
+        match self {
+            MeasurementRate::Ms50 => 50,
+            MeasurementRate::Ms100 => 100,
+            MeasurementRate::Ms200 => 200,
+            MeasurementRate::Ms500 => 500,
+            MeasurementRate::Ms1000 => 1000,
+            MeasurementRate::Ms2000 => 2000,
+        }
+    }
+
+    fn bits(&self) -> u8 {
+        match self {
+            MeasurementRate::Ms50 => 0b000,
+            MeasurementRate::Ms100 => 0b001,
+            MeasurementRate::Ms200 => 0b010,
+            MeasurementRate::Ms500 => 0b011,
+            MeasurementRate::Ms1000 => 0b100,
+            MeasurementRate::Ms2000 => 0b101,
+        }
+    }
+}
+
+impl fmt::Display for MeasurementRate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ms", self.millis())
+    }
+}
+
+/// Settings of a single sensor, defaulting to those the firmware starts the sensors with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorConfig {
+    #[serde(default)]
+    pub gain: Gain,
+    #[serde(default)]
+    pub integration_time: IntegrationTime,
+    #[serde(default)]
+    pub measurement_rate: MeasurementRate,
+}
+
+impl SensorConfig {
+    /// Returns the value of the `ALS_CONTR` register, setting the gain and the active mode.
+    pub fn control_register(&self) -> u8 {
+        (self.gain.bits() << 2) | 0b1
+    }
+
+    /// Returns the value of the `ALS_MEAS_RATE` register.
+    pub fn measurement_register(&self) -> u8 {
+        (self.integration_time.bits() << 3) | self.measurement_rate.bits()
+    }
+
+    /// Sets the integration time, slowing the measurement rate down to keep up with it.
+    pub fn set_integration_time(&mut self, integration_time: IntegrationTime) {
+        self.integration_time = integration_time;
+        if self.measurement_rate.millis() < integration_time.millis() {
+            self.measurement_rate = MeasurementRate::ALL
+                .into_iter()
+                .find(|rate| rate.millis() >= integration_time.millis())
+                .unwrap_or(MeasurementRate::Ms2000);
+        }
+    }
+
+    /// Returns the illuminance in lux of `channels` measured with these settings. The default
+    /// settings read the same lux as older firmware.
+    pub fn lux(&self, channels: &RawChannels) -> f64 {
+        channels.lux(self.gain.factor(), self.integration_time.factor())
+    }
+}
+
+/// Raw counts of the two photodiodes of an LTR-303ALS ambient light sensor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RawChannels {
+    /// Visible and infrared channel.
+    pub ch0: u16,
+    /// Infrared channel.
+    pub ch1: u16,
+}
+
+impl RawChannels {
+    /// Returns the share of infrared light, `CH1 / (CH0 + CH1)`.
+    pub fn ir_ratio(&self) -> Option<f64> {
+        ir_ratio(self.ch0 as f64, self.ch1 as f64)
+    }
+
+    /// Returns the illuminance in lux measured with `gain` and `integration_factor`.
+    pub fn lux(&self, gain: f64, integration_factor: f64) -> f64 {
+        lux(self.ch0 as f64, self.ch1 as f64, gain, integration_factor)
+    }
+}
+
+/// Returns the share of infrared light in counts `ch0` and `ch1`, or `None` when both are zero.
+pub fn ir_ratio(ch0: f64, ch1: f64) -> Option<f64> {
+    (ch0 + ch1 > 0.0).then(|| ch1 / (ch0 + ch1))
+}
+
+/// Returns the illuminance in lux of the counts `ch0` and `ch1` with the formula of the datasheet.
+pub fn lux(ch0: f64, ch1: f64, gain: f64, integration_factor: f64) -> f64 {
+    let Some(ratio) = ir_ratio(ch0, ch1) else {
+        return 0.0;
+    };
+
+    let weighted = if ratio < 0.45 {
+        1.7743 * ch0 + 1.1059 * ch1
+    } else if ratio < 0.64 {
+        4.2785 * ch0 - 1.9548 * ch1
+    } else if ratio < 0.85 {
+        0.5926 * ch0 + 0.1185 * ch1
+    } else {
+        0.0
+    };
+    weighted / gain / integration_factor
+}
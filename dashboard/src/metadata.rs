@@ -0,0 +1,119 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use serialport::{SerialPortInfo, SerialPortType};
+use crate::calibration::Calibration;
+use crate::hardware::HardwareMapping;
+use crate::interleave::DarkInterleave;
+use crate::microplate::MicroPlate;
+use crate::sample::Samples;
+use crate::sensors::SensorSettings;
+use crate::sequence::SequenceInfo;
+
+/// Why a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    /// Every step of the sequence ran to completion.
+    Completed,
+    /// The run was stopped from the menu bar.
+    Stopped,
+    /// The dashboard was closed while the run was in progress.
+    Closed,
+}
+
+/// USB identity of the board the run was recorded with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub vid: u16,
+    pub pid: u16,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+}
+
+/// Blanking settings of a run recorded with dark readings, see [`DarkInterleave`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DarkReadings {
+    pub interval_s: f64,
+    pub duration_s: f64,
+    pub settle_s: f64,
+    /// Total time the LEDs were blanked during the run.
+    pub blanked_s: f64,
+}
+
+/// Settings of a run, written as a JSON sidecar next to its results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMetadata {
+    pub app_version: String,
+    pub results_file: String,
+    pub operator: String,
+    pub notes: String,
+    pub serial_port: Option<String>,
+    pub device: Option<DeviceInfo>,
+    pub hardware: HardwareMapping,
+    pub sampling_interval_s: f64,
+    pub dark_readings: Option<DarkReadings>,
+    pub started: DateTime<Local>,
+    pub stopped: Option<DateTime<Local>>,
+    pub stop_reason: Option<StopReason>,
+    pub sequence_info: SequenceInfo,
+    pub sequence: Vec<MicroPlate>,
+    /// What was put in each well, keyed by well label.
+    #[serde(default)]
+    pub samples: Samples,
+    /// Gain, integration time and measurement rate the sensors were set to.
+    #[serde(default)]
+    pub sensors: SensorSettings,
+    /// Calibration of the sensors applied to the readings, if any.
+    #[serde(default)]
+    pub calibration: Option<Calibration>,
+}
+
+impl RunMetadata {
+    /// Returns the path of the sidecar belonging to the results file at `results_file`.
+    pub fn path_for(results_file: &Path) -> PathBuf {
+        results_file.with_extension("json")
+    }
+
+    pub fn set_port(&mut self, port: Option<&SerialPortInfo>) {
+        self.serial_port = port.map(|port| port.port_name.clone());
+        self.device = port.and_then(|port| match &port.port_type {
+            SerialPortType::UsbPort(info) => Some(DeviceInfo {
+                vid: info.vid,
+                pid: info.pid,
+                serial_number: info.serial_number.clone(),
+                manufacturer: info.manufacturer.clone(),
+                product: info.product.clone(),
+            }),
+            _ => None,
+        });
+    }
+
+    pub fn set_dark_readings(&mut self, interleave: &DarkInterleave) {
+        self.dark_readings = interleave.enabled.then(|| DarkReadings {
+            interval_s: interleave.interval.as_secs_f64(),
+            duration_s: interleave.duration.as_secs_f64(),
+            settle_s: interleave.settle.as_secs_f64(),
+            blanked_s: interleave.blanked().as_secs_f64(),
+        });
+    }
+
+    /// Records the end of the run.
+    pub fn stop(&mut self, reason: StopReason) {
+        self.stopped = Some(Local::now());
+        self.stop_reason = Some(reason);
+    }
+
+    /// Writes the sidecar next to the results file at `results_file`.
+    pub fn save(&self, results_file: &Path) -> io::Result<()> {
+        let json_data = serde_json::to_string_pretty(self)?;
+        fs::write(RunMetadata::path_for(results_file), json_data)
+    }
+
+    pub fn load(path: &Path) -> io::Result<RunMetadata> {
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
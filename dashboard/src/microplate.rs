@@ -1,54 +1,143 @@
+use crate::design::DesignSpec;
+use crate::geometry::PlateGeometry;
+use crate::heatmap::Shading;
 use crate::microwell::MicroWell;
+use crate::selection::Selection;
 use crate::wavelength::Wavelength;
 use eframe::egui;
 use serde::{Serialize, Deserialize};
 
 pub const BOX_SIDE: f32 = 50.0;
-pub const CELL_RADIUS: f32 = BOX_SIDE * 0.4;
-pub const MICRO_WELL_NUM: i32 = 5;
+/// Smallest well drawn when a large plate has to be shrunk to fit the window.
+const MIN_BOX_SIDE: f32 = 28.0;
+/// Width of the row headers and height of the column headers.
+const HEADER_SIDE: f32 = 20.0;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MicroPlate {
+    #[serde(default)]
+    pub name: String,
+    /// Colour marking the step in the step list and plots.
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
     pub brightness: f32,
     pub wavelength: Wavelength,
     pub duration: u64,
     pub wells: Vec<MicroWell>,
+    #[serde(default)]
+    pub design: Option<DesignSpec>,
+    /// Layout of [`MicroPlate::wells`], which are stored in row-major order.
+    #[serde(default)]
+    pub geometry: PlateGeometry,
 }
 
 impl Default for MicroPlate {
     fn default() -> Self {
-        let mut wells = vec![];
-
-        for i in 0u8..5 {
-            let mut letter = 'D';
-            for _ in 0u8..5 {
-                wells.push(MicroWell::new(format!("{}{}", letter, 5-i)));
-                letter = ((letter as u8) + 1) as char;
-            }
-        }
+        MicroPlate::with_geometry(PlateGeometry::default())
+    }
+}
 
+impl MicroPlate {
+    /// Returns an empty step on a plate laid out as `geometry`.
+    pub fn with_geometry(geometry: PlateGeometry) -> Self {
         Self {
+            name: String::new(),
+            color: None,
             brightness: 50.0,
             wavelength: Wavelength::default(),
             duration: 5,
-            wells
+            wells: MicroPlate::wells_for(&geometry),
+            design: None,
+            geometry,
         }
     }
-}
 
-impl MicroPlate {
-    pub fn show(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+    fn wells_for(geometry: &PlateGeometry) -> Vec<MicroWell> {
+        let mut wells = vec![];
+        for row in 0..geometry.rows {
+            for col in 0..geometry.columns {
+                let mut well = MicroWell::new(geometry.label(row, col));
+                well.disabled = !geometry.is_populated(row, col);
+                wells.push(well);
+            }
+        }
+        wells
+    }
+
+    /// Lays the step out as `geometry`, keeping the settings of the wells whose label is found
+    /// on both plates.
+    pub fn set_geometry(&mut self, geometry: PlateGeometry) {
+        let mut wells = MicroPlate::wells_for(&geometry);
+        for well in wells.iter_mut() {
+            well.brightness = self.brightness;
+            well.wavelength = self.wavelength.clone();
+            if let Some(previous) = self.wells.iter().find(|previous| previous.label == well.label) {
+                if !well.disabled && !previous.disabled {
+                    well.led_on = previous.led_on;
+                    well.wavelength = previous.wavelength.clone();
+                }
+            }
+        }
+
+        self.wells = wells;
+        self.geometry = geometry;
+    }
+
+    /// Returns the indices of every well that can be lit and measured, in row-major order.
+    pub fn usable_wells(&self) -> Vec<usize> {
+        self.wells
+            .iter()
+            .enumerate()
+            .filter(|(_, well)| !well.disabled)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the name of the step at position `idx`, or its position when it has none.
+    pub fn display_name(&self, idx: usize) -> String {
+        if self.name.is_empty() {
+            format!("Well Pattern {}", idx + 1)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Shows the settings and wells of the step, see [`MicroPlate::show_wells`].
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        selection: &mut Selection,
+        shading: &Shading,
+    ) {
         ui.vertical(|ui| {
             egui::Grid::new("my_grid")
                 .num_columns(2)
                 .spacing([40.0, 4.0])
                 .striped(true)
                 .show(ui, |ui| {
+                    ui.label("name: ");
+                    ui.text_edit_singleline(&mut self.name);
+                    ui.end_row();
+
+                    ui.label("colour: ");
+                    ui.horizontal(|ui| {
+                        let mut custom = self.color.is_some();
+                        if ui.checkbox(&mut custom, "").changed() {
+                            self.color = custom.then_some([100, 116, 139]);
+                        }
+                        if let Some(color) = &mut self.color {
+                            ui.color_edit_button_srgb(color);
+                        }
+                    });
+                    ui.end_row();
+
                     ui.label("duration [s]: ");
                     ui.add(egui::DragValue::new(&mut self.duration).speed(1.0));
                     ui.end_row();
 
                     ui.label("wavelength: ");
+                    let previous_wavelength = self.wavelength.clone();
                     egui::ComboBox::from_label("")
                         .selected_text(format!("{:?}", self.wavelength))
                         .show_ui(ui, |ui| {
@@ -57,6 +146,13 @@ impl MicroPlate {
                             ui.selectable_value(&mut self.wavelength, Wavelength::W630nm, "630nm");
                             ui.selectable_value(&mut self.wavelength, Wavelength::W850nm, "850nm");
                         });
+                    if self.wavelength != previous_wavelength {
+                        // Only override the wells when the plate wavelength actually changes so
+                        // that per-well wavelengths (e.g. from the design wizard) are kept.
+                        self.wells
+                            .iter_mut()
+                            .for_each(|well| well.wavelength = self.wavelength.clone());
+                    }
                     ui.end_row();
 
                     ui.label("brightness: ");
@@ -64,32 +160,118 @@ impl MicroPlate {
                     ui.end_row();
                 });
 
-            for row in 0..MICRO_WELL_NUM {
+            self.show_wells(ctx, ui, selection, shading);
+        });
+    }
+
+    /// Shows the wells of the step with row and column headers.
+    pub fn show_wells(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        selection: &mut Selection,
+        shading: &Shading,
+    ) {
+        ui.vertical(|ui| {
+            // Shrink the wells of large plates so that every column fits in the window
+            let spacing = ui.spacing().item_spacing.x;
+            let side = ((ui.available_width() - HEADER_SIDE) / self.geometry.columns.max(1) as f32 - spacing)
+                .clamp(MIN_BOX_SIDE, BOX_SIDE);
+            let modifiers = ui.input(|i| i.modifiers);
+            let geometry = &self.geometry;
+
+            ui.horizontal(|ui| {
+                ui.add_space(HEADER_SIDE + spacing);
+                for col in 0..geometry.columns {
+                    let header = egui::Button::new(geometry.column_header(col)).frame(false);
+                    if ui.add_sized([side, HEADER_SIDE], header).clicked() {
+                        selection.select_column(geometry, col, modifiers.command);
+                    }
+                }
+            });
+
+            let mut cells = vec![];
+            for row in 0..geometry.rows {
                 ui.horizontal(|ui| {
-                    for col in 0..MICRO_WELL_NUM {
-                        let idx = row as usize * 5 + col as usize;
-                        if (col == 1 && row != 1)
-                            || (col != 1 && row == 1)
-                            || (row == 3 && col == 0)
-                        {
-                            self.wells[idx].disabled = true;
-                        }
+                    let header = egui::Button::new(geometry.row_header(row)).frame(false);
+                    if ui.add_sized([HEADER_SIDE, side + 15.0], header).clicked() {
+                        selection.select_row(geometry, row, modifiers.command);
+                    }
+
+                    for col in 0..geometry.columns {
+                        let idx = geometry.index(row, col);
+                        let Some(well) = self.wells.get_mut(idx) else {
+                            continue;
+                        };
 
-                        self.wells[idx].brightness = self.brightness;
-                        self.wells[idx].wavelength = self.wavelength.clone();
-                        self.wells[idx].show(ctx, ui);
+                        well.brightness = self.brightness;
+                        let response = well.show(
+                            ctx,
+                            ui,
+                            side,
+                            selection.contains(&well.label),
+                            shading.wells.get(&well.label),
+                        );
+                        cells.push((row, col, response.rect));
+
+                        if well.disabled {
+                            continue;
+                        }
+                        if response.drag_started() {
+                            selection.start_drag(row, col);
+                        } else if response.clicked() {
+                            if modifiers.shift {
+                                selection.extend_to(geometry, row, col, modifiers.command);
+                            } else if modifiers.command {
+                                selection.toggle(geometry, row, col);
+                            } else {
+                                well.led_on = !well.led_on;
+                            }
+                        } else if response.secondary_clicked() {
+                            selection.select_only(geometry, row, col);
+                        }
                     }
                 });
             }
+
+            if selection.is_dragging() {
+                let pointer = ui.input(|i| i.pointer.interact_pos());
+                if let Some((row, col, _)) = pointer
+                    .and_then(|pointer| cells.iter().find(|(_, _, rect)| rect.contains(pointer)))
+                {
+                    selection.drag_to(geometry, *row, *col, modifiers.command);
+                }
+                if !ui.input(|i| i.pointer.any_down()) {
+                    selection.stop_drag();
+                }
+            }
+
+            shading.show_legend(ui);
         });
     }
 
-    pub fn clear(&mut self) {
-        for row in 0..MICRO_WELL_NUM {
-            for col in 0..MICRO_WELL_NUM {
-                let idx = row as usize * 5 + col as usize;
-                self.wells[idx].measurement = 0.0;
+    /// Returns the selected usable wells of the step, or all of them when none is selected.
+    pub fn copy_wells(&self, selection: &Selection) -> Vec<MicroWell> {
+        self.wells
+            .iter()
+            .filter(|well| !well.disabled && (selection.is_empty() || selection.contains(&well.label)))
+            .cloned()
+            .collect()
+    }
+
+    /// Gives the usable wells the LED state and wavelength of the copied well of the same label.
+    pub fn paste_wells(&mut self, copied: &[MicroWell]) {
+        for well in self.wells.iter_mut().filter(|well| !well.disabled) {
+            if let Some(copy) = copied.iter().find(|copy| copy.label == well.label) {
+                well.led_on = copy.led_on;
+                well.wavelength = copy.wavelength.clone();
             }
         }
     }
+
+    pub fn clear(&mut self) {
+        for well in self.wells.iter_mut() {
+            well.measurement = 0.0;
+        }
+    }
 }
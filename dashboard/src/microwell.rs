@@ -1,7 +1,7 @@
 use crate::colors::*;
-use crate::microplate::{BOX_SIDE, CELL_RADIUS};
+use crate::heatmap::WellShade;
 use eframe::egui;
-use eframe::egui::{Align2, Color32, Pos2, Sense, Stroke, TextStyle, Ui};
+use eframe::egui::{Align2, Color32, Pos2, Response, Sense, Stroke, TextStyle, Ui};
 use crate::wavelength::Wavelength;
 use serde::{Serialize, Deserialize};
 
@@ -40,10 +40,20 @@ impl MicroWell {
         }
     }
 
-    pub fn show(&mut self, ctx: &egui::Context, ui: &mut Ui) -> bool {
+    /// Draws the well in a box `side` points wide, in the heatmap `shade` if any.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        ui: &mut Ui,
+        side: f32,
+        selected: bool,
+        shade: Option<&WellShade>,
+    ) -> Response {
+        let radius = side * 0.4;
+
         // Allocate some space for the button
         let (response, painter) =
-            ui.allocate_painter(egui::Vec2::new(BOX_SIDE, BOX_SIDE + 15.0), Sense::click());
+            ui.allocate_painter(egui::Vec2::new(side, side + 15.0), Sense::click_and_drag());
 
         // Calculate the center and radius of the circle
         let center = Pos2 {
@@ -54,6 +64,8 @@ impl MicroWell {
         // Choose color based on hover state
         let fill_color = if self.disabled {
             COLOR_SLATE_700
+        } else if let Some(shade) = shade {
+            shade.color
         } else if response.hovered() {
             if self.led_on {
                 let color = self.wavelength.get_hovered_color();
@@ -79,12 +91,15 @@ impl MicroWell {
         };
 
         // Draw the circle with the chosen color
-        painter.circle_filled(center, CELL_RADIUS, fill_color);
+        painter.circle_filled(center, radius, fill_color);
 
-        // Add white outline if hovered
-        if response.hovered() && !self.disabled {
+        // Add white outline if hovered and a thicker one if selected
+        if selected {
+            let stroke = Stroke::new(3.0, COLOR_SLATE_100);
+            painter.circle_stroke(center, radius, stroke);
+        } else if response.hovered() && !self.disabled {
             let stroke = Stroke::new(1.0, Color32::from_rgb(255, 255, 255));
-            painter.circle_stroke(center, CELL_RADIUS, stroke);
+            painter.circle_stroke(center, radius, stroke);
         }
 
         if !self.disabled {
@@ -96,7 +111,7 @@ impl MicroWell {
             painter.text(
                 text_pos,
                 Align2::CENTER_CENTER,
-                self.measurement,
+                shade.map(|shade| shade.text.clone()).unwrap_or_else(|| self.measurement.to_string()),
                 TextStyle::Small.resolve(&ctx.style()),
                 COLOR_SLATE_100,
             );
@@ -105,7 +120,7 @@ impl MicroWell {
 
         let label_pos = Pos2 {
             x: center.x,
-            y: center.y + 30.0,
+            y: center.y + radius + 10.0,
         };
         painter.text(
             label_pos,
@@ -115,12 +130,6 @@ impl MicroWell {
             COLOR_SLATE_100,
         );
 
-        // Check for interactions
-        if response.clicked() {
-            self.led_on = !self.led_on;
-            return true;
-        }
-
-        false
+        response
     }
 }
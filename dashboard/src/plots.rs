@@ -0,0 +1,233 @@
+use std::collections::{BTreeMap, BTreeSet};
+use eframe::egui;
+use eframe::egui::plot::{Legend, Line, LineStyle, Plot, PlotPoints, Polygon, VLine};
+use eframe::egui::Color32;
+use crate::density::{Quantities, Quantity};
+use crate::growth::GrowthFits;
+use crate::sample::Samples;
+use crate::timeseries::{by_time, Timeline};
+
+/// Readings of a run to plot.
+pub struct PlotSource<'a> {
+    /// Name prefixed to the lines of the run when several runs are overlaid.
+    pub name: &'a str,
+    pub timeline: &'a Timeline,
+    pub samples: &'a Samples,
+    /// Growth curves drawn over the readings of the wells they were fitted to.
+    pub fits: Option<&'a GrowthFits>,
+}
+
+/// Window plotting the readings of every well over time.
+#[derive(Debug, Default)]
+pub struct PlotWindow {
+    pub open: bool,
+    quantity: Quantity,
+    view: PlotView,
+}
+
+impl PlotWindow {
+    pub fn show(&mut self, ctx: &egui::Context, title: &str, quantities: &Quantities, samples: &Samples) {
+        let mut open = self.open;
+        egui::Window::new(title)
+            .open(&mut open)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                show_quantity(ui, title, &mut self.quantity);
+                let source = PlotSource { name: "", timeline: quantities.get(self.quantity), samples, fits: None };
+                self.view.show(ui, title, &[source], None);
+            });
+        self.open = open;
+    }
+}
+
+/// Shows a choice of the quantity to plot or analyse.
+pub fn show_quantity(ui: &mut egui::Ui, id: &str, quantity: &mut Quantity) {
+    egui::ComboBox::from_id_source(format!("{}_quantity", id))
+        .selected_text(quantity.to_string())
+        .show_ui(ui, |ui| {
+            for option in Quantity::ALL {
+                ui.selectable_value(quantity, option, option.to_string());
+            }
+        })
+        .response
+        .on_hover_text("Transmittance and OD need blank wells lit with the same wavelength.");
+}
+
+/// Plot of the readings of every well of one or more runs over time.
+#[derive(Debug, Default)]
+pub struct PlotView {
+    /// Labels of the wells left out of the plot.
+    hidden: BTreeSet<String>,
+    /// Plots the mean of every replicate group instead of the individual wells.
+    group_by_replicate: bool,
+    /// Fits the plot to the data on the next frame.
+    reset: bool,
+}
+
+impl PlotView {
+    /// Shows the plot of `sources` with a line at `cursor` and returns the time clicked on.
+    pub fn show(&mut self, ui: &mut egui::Ui, id: &str, sources: &[PlotSource], cursor: Option<f64>) -> Option<f64> {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.group_by_replicate, "Group by replicate group");
+            if ui.button("Autoscale").clicked() {
+                self.reset = true;
+            }
+        });
+
+        let labels: BTreeSet<&String> = sources.iter().flat_map(|source| source.timeline.series.keys()).collect();
+        egui::SidePanel::left(format!("{}_wells", id))
+            .resizable(false)
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if ui.button("All").clicked() {
+                        self.hidden.clear();
+                    }
+                    if ui.button("None").clicked() {
+                        self.hidden = labels.iter().map(|label| label.to_string()).collect();
+                    }
+                    for label in &labels {
+                        let mut visible = !self.hidden.contains(*label);
+                        if ui.checkbox(&mut visible, label.as_str()).changed() {
+                            if visible {
+                                self.hidden.remove(*label);
+                            } else {
+                                self.hidden.insert(label.to_string());
+                            }
+                        }
+                    }
+                });
+            });
+
+        self.show_plot(ui, id, sources, cursor)
+    }
+
+    fn show_plot(&mut self, ui: &mut egui::Ui, id: &str, sources: &[PlotSource], cursor: Option<f64>) -> Option<f64> {
+        let lines: Vec<(String, Vec<[f64; 2]>)> = sources
+            .iter()
+            .flat_map(|source| {
+                self.lines(source.timeline, source.samples)
+                    .into_iter()
+                    .map(move |(name, points)| match sources.len() {
+                        1 => (name, points),
+                        _ => (format!("{}: {}", source.name, name), points),
+                    })
+            })
+            .collect();
+        let curves: Vec<(String, Vec<[f64; 2]>)> = sources
+            .iter()
+            .filter(|_| !self.group_by_replicate)
+            .filter_map(|source| source.fits.map(|fits| (source, fits)))
+            .flat_map(|(source, fits)| {
+                fits.iter()
+                    .filter(|(label, _)| !self.hidden.contains(*label))
+                    .filter_map(|(label, fit)| fit.as_ref().ok().map(|fit| (label, fit)))
+                    .map(move |(label, fit)| {
+                        let name = match sources.len() {
+                            1 => format!("{} ({})", label, fit.model),
+                            _ => format!("{}: {} ({})", source.name, label, fit.model),
+                        };
+                        (name, fit.curve(200))
+                    })
+            })
+            .collect();
+
+        let (min, max) = lines
+            .iter()
+            .flat_map(|(_, points)| points.iter().map(|point| point[1]))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), y| (min.min(y), max.max(y)));
+        let last = lines
+            .iter()
+            .filter_map(|(_, points)| points.last().map(|point| point[0]))
+            .fold(0.0, f64::max);
+
+        let mut plot = Plot::new(format!("{}_plot", id))
+            .legend(Legend::default())
+            .x_axis_formatter(|x, _| format!("{:.0} s", x));
+        if self.reset {
+            plot = plot.reset();
+            self.reset = false;
+        }
+
+        let steps = sources.first().map(|source| source.timeline.steps.as_slice()).unwrap_or_default();
+        let response = plot.show(ui, |plot_ui| {
+            // Shade every step with its colour, or the colour of its wavelength dimmed by its
+            // brightness, over the range of the plotted readings
+            if min <= max {
+                for step in steps {
+                    let end = step.end.unwrap_or(last).max(step.start);
+                    let color = match step.color {
+                        Some([r, g, b]) => Color32::from_rgb(r, g, b),
+                        None => step.wavelength.get_color(),
+                    };
+                    let alpha = 0.05 + step.brightness / 100.0 * 0.15;
+
+                    plot_ui.polygon(
+                        Polygon::new(PlotPoints::new(vec![
+                            [step.start, min],
+                            [end, min],
+                            [end, max],
+                            [step.start, max],
+                        ]))
+                        .color(color)
+                        .fill_alpha(alpha)
+                        .width(0.0)
+                        .name(&step.name),
+                    );
+                }
+            }
+
+            for (name, points) in lines {
+                plot_ui.line(Line::new(PlotPoints::new(points)).name(name));
+            }
+            for (name, points) in curves {
+                plot_ui.line(Line::new(PlotPoints::new(points)).name(name).style(LineStyle::dashed_loose()));
+            }
+
+            if let Some(cursor) = cursor {
+                plot_ui.vline(VLine::new(cursor).color(Color32::WHITE));
+            }
+
+            plot_ui
+                .plot_clicked()
+                .then(|| plot_ui.pointer_coordinate().map(|point| point.x))
+                .flatten()
+        });
+
+        response.inner
+    }
+
+    /// Returns the named lines to plot, one per visible well or replicate group.
+    fn lines(&self, timeline: &Timeline, samples: &Samples) -> Vec<(String, Vec<[f64; 2]>)> {
+        let visible = timeline
+            .series
+            .iter()
+            .filter(|(label, series)| !self.hidden.contains(*label) && !series.is_empty());
+
+        if !self.group_by_replicate {
+            return visible
+                .map(|(label, series)| (label.clone(), series.points()))
+                .collect();
+        }
+
+        let mut groups: BTreeMap<String, Vec<Vec<[f64; 2]>>> = BTreeMap::new();
+        for (label, series) in visible {
+            let group = samples
+                .get(label)
+                .map(|sample| sample.replicate_group.clone())
+                .filter(|group| !group.is_empty())
+                .unwrap_or_else(|| label.clone());
+            groups.entry(group).or_default().push(series.points());
+        }
+
+        groups
+            .into_iter()
+            .map(|(group, members)| {
+                let mean = by_time(members.into_iter().enumerate())
+                    .into_iter()
+                    .map(|(time, values)| [time, values.values().sum::<f64>() / values.len() as f64])
+                    .collect();
+                (group, mean)
+            })
+            .collect()
+    }
+}
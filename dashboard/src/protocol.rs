@@ -0,0 +1,61 @@
+use crate::ltr303::{Gain, RawChannels, SensorConfig};
+
+/// Bit of the first byte of a frame set for the second multiplexer.
+const MUX_BIT: u8 = 0b1000_0000;
+/// Bit of the first byte of a frame set when it carries the raw channels of the sensor.
+const RAW_BIT: u8 = 0b0100_0000;
+/// Bits of the first byte of a raw frame holding the gain the sensor measured with.
+const GAIN_MASK: u8 = 0b0011_1000;
+const INDEX_MASK: u8 = 0b0000_0111;
+/// First byte of the command configuring a sensor.
+const CONFIGURE_SENSOR: u8 = 0b1111_1110;
+
+/// A sensor frame sent by the board, either the lux of older firmware or raw counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    Lux { mux: u8, idx: u8, value: u16 },
+    /// Raw counts along with the gain they were measured with.
+    Raw { mux: u8, idx: u8, channels: RawChannels, gain: Option<Gain> },
+}
+
+impl Frame {
+    /// Returns the number of bytes following the first byte, `header`, of a frame.
+    pub fn body_length(header: u8) -> usize {
+        if header & RAW_BIT != 0 {
+            4
+        } else {
+            2
+        }
+    }
+
+    /// Decodes the frame starting with `header` and followed by `body`.
+    pub fn decode(header: u8, body: &[u8]) -> Frame {
+        let mux = (header & MUX_BIT) >> 7;
+        let idx = header & INDEX_MASK;
+        let word = |offset: usize| u16::from_le_bytes([body[offset], body[offset + 1]]);
+
+        if header & RAW_BIT != 0 {
+            let channels = RawChannels { ch0: word(0), ch1: word(2) };
+            Frame::Raw { mux, idx, channels, gain: Gain::from_bits((header & GAIN_MASK) >> 3) }
+        } else {
+            Frame::Lux { mux, idx, value: word(0) }
+        }
+    }
+
+    /// Returns the multiplexer and channel of the sensor the frame was read from.
+    pub fn sensor(&self) -> (u8, u8) {
+        match self {
+            Frame::Lux { mux, idx, .. } | Frame::Raw { mux, idx, .. } => (*mux, *idx),
+        }
+    }
+}
+
+/// Returns the command setting `sensor` behind multiplexer `mux` to `config`.
+pub fn configure_sensor(mux: u8, sensor: u8, config: &SensorConfig) -> [u8; 4] {
+    [
+        CONFIGURE_SENSOR,
+        (mux << 7) | (sensor & INDEX_MASK),
+        config.control_register(),
+        config.measurement_register(),
+    ]
+}
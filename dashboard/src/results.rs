@@ -0,0 +1,633 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use crate::density::{optical_density, Blanks};
+use crate::interleave::DarkInterleave;
+use crate::ltr303;
+use crate::microplate::MicroPlate;
+use crate::sampling::Sampler;
+use crate::timeseries::{StepSpan, Timeline};
+use crate::wavelength::Wavelength;
+
+/// Version of the results CSV layout, written on the first line of every results file.
+pub const FORMAT_VERSION: u32 = 6;
+
+/// Version of the layout legacy results files are converted to.
+pub const LEGACY_VERSION: u32 = 2;
+
+const VERSION_PREFIX: &str = "# tinywell results format_version=";
+
+/// Columns written for every well, with the format version that added them.
+const WELL_COLUMNS: [(&str, u32); 14] = [
+    ("LED", 2),
+    ("wavelength", 2),
+    ("brightness", 2),
+    ("mean", 2),
+    ("min", 2),
+    ("max", 2),
+    ("count", 2),
+    ("uncalibrated", 6),
+    ("ch0", 3),
+    ("ch1", 3),
+    ("IR ratio", 3),
+    ("gain", 5),
+    ("saturated", 5),
+    ("range change", 5),
+];
+
+/// Returns the names of the columns written for every well in results files of `version`.
+fn well_columns(version: u32) -> impl Iterator<Item = &'static str> {
+    WELL_COLUMNS.into_iter().filter(move |(_, added)| *added <= version).map(|(name, _)| name)
+}
+
+/// Returns the format version and column names written at the top of a results file.
+///
+/// Version 2 holds the LED, wavelength, brightness, mean, minimum, maximum and count of each
+/// well, with its dark and corrected readings when dark readings are interleaved. Version 3
+/// added the `ch0`, `ch1` and `IR ratio` columns, version 4 the `transmittance` and `OD`
+/// columns, version 5 the `gain`, `saturated` and `range change` columns and version 6 the
+/// `uncalibrated` column.
+pub fn format_header(plate: &MicroPlate, interleave: &DarkInterleave, blanks: bool) -> String {
+    let mut columns = vec![
+        "Time".to_string(),
+        "Elapsed [s]".to_string(),
+        "Step".to_string(),
+        "Step Name".to_string(),
+        "Brightness [%]".to_string(),
+        "Step Duration [s]".to_string(),
+    ];
+    if interleave.enabled {
+        columns.push("Blanked [s]".to_string());
+        columns.push("Lit Fraction".to_string());
+    }
+
+    for well in plate.wells.iter().filter(|well| !well.disabled) {
+        for column in well_columns(FORMAT_VERSION) {
+            columns.push(format!("{} {}", well.label, column));
+        }
+        if interleave.enabled {
+            columns.push(format!("{} dark", well.label));
+            columns.push(format!("{} corrected", well.label));
+        }
+        if blanks {
+            columns.push(format!("{} transmittance", well.label));
+            columns.push(format!("{} OD", well.label));
+        }
+    }
+
+    format!("{}{}\n{}", VERSION_PREFIX, FORMAT_VERSION, columns.join(","))
+}
+
+/// Returns the results row of `plate`, the step at index `step`, over the window of `sampler`.
+pub fn format_row(
+    time: DateTime<Local>,
+    elapsed: Duration,
+    step: usize,
+    plate: &MicroPlate,
+    sampler: &Sampler,
+    interleave: &DarkInterleave,
+    blanks: Option<&Blanks>,
+) -> String {
+    let format = |value: Option<f32>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    let mut columns = vec![
+        time.to_rfc3339(),
+        format!("{:.3}", elapsed.as_secs_f64()),
+        (step + 1).to_string(),
+        escape(&plate.display_name(step)),
+        plate.brightness.to_string(),
+        plate.duration.to_string(),
+    ];
+    if interleave.enabled {
+        columns.push(interleave.blanked().as_secs_f64().to_string());
+        columns.push(interleave.lit_fraction(elapsed).to_string());
+    }
+
+    for idx in plate.usable_wells() {
+        let well = &plate.wells[idx];
+        let aggregate = sampler.get(idx);
+
+        columns.push(if well.led_on { "on" } else { "off" }.to_string());
+        columns.push(well.wavelength.to_string());
+        columns.push(plate.brightness.to_string());
+        columns.push(format(aggregate.mean()));
+        columns.push(format(aggregate.min()));
+        columns.push(format(aggregate.max()));
+        columns.push(aggregate.count().to_string());
+        columns.push(format(sampler.uncalibrated(idx).mean()));
+
+        let [ch0, ch1] = sampler.channels(idx).map(|channel| channel.mean());
+        let ir_ratio = ch0.zip(ch1).and_then(|(ch0, ch1)| ltr303::ir_ratio(ch0 as f64, ch1 as f64));
+        columns.push(format(ch0));
+        columns.push(format(ch1));
+        columns.push(ir_ratio.map(|ratio| ratio.to_string()).unwrap_or_default());
+
+        let range = sampler.range(idx);
+        columns.push(range.gain.map(|gain| gain.factor().to_string()).unwrap_or_default());
+        columns.push(range.saturated.to_string());
+        columns.push(
+            range
+                .changes
+                .iter()
+                .map(|(from, to)| format!("{}→{}", from.factor(), to.factor()))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+
+        if interleave.enabled {
+            let dark = interleave.background(idx);
+            let corrected = aggregate.mean().zip(dark).map(|(lit, dark)| lit - dark);
+            columns.push(format(dark));
+            columns.push(format(corrected));
+        }
+
+        if let Some(blanks) = blanks {
+            let transmittance = reading(idx, sampler, interleave)
+                .and_then(|reading| blanks.transmittance(plate, idx, reading as f64));
+            columns.push(transmittance.map(|t| t.to_string()).unwrap_or_default());
+            columns.push(transmittance.map(|t| optical_density(t).to_string()).unwrap_or_default());
+        }
+    }
+
+    columns.join(",")
+}
+
+/// Returns the reading of the well at `idx` over the sampling window of `sampler`.
+pub fn reading(idx: usize, sampler: &Sampler, interleave: &DarkInterleave) -> Option<f32> {
+    let mean = sampler.get(idx).mean()?;
+    match interleave.background(idx) {
+        Some(dark) if interleave.enabled => Some(mean - dark),
+        _ => Some(mean),
+    }
+}
+
+/// Returns the format version of a results file from its contents, `1` being the original
+/// unversioned layout.
+pub fn format_version(contents: &str) -> Option<u32> {
+    match contents.lines().next() {
+        Some(line) if line.starts_with(VERSION_PREFIX) => line[VERSION_PREFIX.len()..].trim().parse().ok(),
+        Some(_) => Some(1),
+        None => None,
+    }
+}
+
+/// Converts an unversioned results file into the version 2 layout, dated from `start` if known.
+pub fn convert_legacy(contents: &str, start: Option<DateTime<Local>>) -> io::Result<String> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut lines = contents.lines();
+    let header: Vec<&str> = lines
+        .next()
+        .ok_or_else(|| invalid("The results file is empty.".to_string()))?
+        .split(',')
+        .collect();
+    if header.len() < 3 || !header[0].starts_with("Brightness") {
+        return Err(invalid("The results file is not in the legacy format.".to_string()));
+    }
+
+    // Legacy columns are either a bare well label or a label followed by a statistic
+    let wells: Vec<(&str, &str)> = header[3..]
+        .iter()
+        .map(|column| column.split_once(' ').unwrap_or((column, "mean")))
+        .collect();
+    let mut labels: Vec<&str> = wells.iter().map(|(label, _)| *label).collect();
+    labels.dedup();
+
+    let mut columns = vec![
+        "Time".to_string(),
+        "Elapsed [s]".to_string(),
+        "Step".to_string(),
+        "Step Name".to_string(),
+        "Brightness [%]".to_string(),
+        "Step Duration [s]".to_string(),
+    ];
+    for label in &labels {
+        for column in well_columns(LEGACY_VERSION) {
+            columns.push(format!("{} {}", label, column));
+        }
+    }
+
+    let mut output = vec![format!("{}{}", VERSION_PREFIX, LEGACY_VERSION), columns.join(",")];
+    let mut step = 0;
+    let mut previous: Option<Vec<&str>> = None;
+
+    for (row, line) in lines.enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let values: Vec<&str> = line.split(',').collect();
+        if values.len() != header.len() {
+            return Err(invalid(format!(
+                "Row {} has {} columns but the header has {}.",
+                row + 2,
+                values.len(),
+                header.len()
+            )));
+        }
+
+        // A new step starts whenever the plate settings change
+        if let Some(previous) = &previous {
+            if previous[..] != values[..3] {
+                step += 1;
+            }
+        }
+
+        let elapsed = Duration::from_secs(row as u64);
+        let mut columns = vec![
+            start
+                .map(|start| (start + chrono::Duration::seconds(row as i64)).to_rfc3339())
+                .unwrap_or_default(),
+            format!("{:.3}", elapsed.as_secs_f64()),
+            (step + 1).to_string(),
+            String::new(),
+            values[0].to_string(),
+            values[2].to_string(),
+        ];
+
+        for label in &labels {
+            let statistic = |name: &str| {
+                wells
+                    .iter()
+                    .position(|well| *well == (*label, name))
+                    .map(|idx| values[idx + 3].to_string())
+                    .unwrap_or_default()
+            };
+
+            columns.push(String::new());
+            columns.push(values[1].to_string());
+            columns.push(values[0].to_string());
+            columns.push(statistic("mean"));
+            columns.push(statistic("min"));
+            columns.push(statistic("max"));
+            columns.push(statistic("count"));
+        }
+
+        output.push(columns.join(","));
+        previous = Some(values[..3].to_vec());
+    }
+
+    Ok(output.join("\n") + "\n")
+}
+
+/// Converts the unversioned results file at `path` into a copy next to it and returns its path.
+pub fn convert_legacy_file(path: &Path) -> io::Result<PathBuf> {
+    let contents = fs::read_to_string(path)?;
+    if format_version(&contents) != Some(1) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The results file is already in a versioned format.",
+        ));
+    }
+
+    let start = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("microfluidic_test_"))
+        .and_then(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%d_%H-%M-%S").ok())
+        .and_then(|time| Local.from_local_datetime(&time).single());
+
+    let converted = convert_legacy(&contents, start)?;
+
+    let mut converted_path = path.to_path_buf();
+    converted_path.set_file_name(format!(
+        "{}_v{}.csv",
+        path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("results"),
+        LEGACY_VERSION
+    ));
+    fs::write(&converted_path, converted)?;
+
+    Ok(converted_path)
+}
+
+/// Columns of a well in a results file.
+struct WellColumns {
+    label: String,
+    led: Option<usize>,
+    wavelength: Option<usize>,
+    mean: usize,
+    corrected: Option<usize>,
+}
+
+/// Reads the readings of a results file back into a [`Timeline`], converting legacy files first.
+pub fn read_timeline(contents: &str) -> io::Result<Timeline> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let converted;
+    let (contents, version) = match format_version(contents) {
+        Some(1) => {
+            converted = convert_legacy(contents, None)?;
+            (converted.as_str(), LEGACY_VERSION)
+        }
+        Some(version) if version <= FORMAT_VERSION => (contents, version),
+        Some(version) => {
+            return Err(invalid(format!(
+                "Results format version {} is not supported, this dashboard reads up to version {}.",
+                version, FORMAT_VERSION
+            )))
+        }
+        None => return Err(invalid("The results file is empty.".to_string())),
+    };
+
+    let body = contents.split_once('\n').map(|(_, body)| body).unwrap_or_default();
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(body.as_bytes());
+    let header = reader.headers()?.clone();
+    let column = |name: &str| header.iter().position(|column| column == name);
+
+    let elapsed = column("Elapsed [s]")
+        .ok_or_else(|| invalid("The results file has no \"Elapsed [s]\" column.".to_string()))?;
+    let (step, step_name, brightness) = (column("Step"), column("Step Name"), column("Brightness [%]"));
+
+    let wells: Vec<WellColumns> = header
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, name)| {
+            let label = name.strip_suffix(" mean")?;
+            Some(WellColumns {
+                label: label.to_string(),
+                led: column(&format!("{} LED", label)),
+                wavelength: column(&format!("{} wavelength", label)),
+                mean: idx,
+                corrected: column(&format!("{} corrected", label)),
+            })
+        })
+        .collect();
+
+    for well in &wells {
+        let missing = well_columns(version).find(|name| column(&format!("{} {}", well.label, name)).is_none());
+        if let Some(missing) = missing {
+            return Err(invalid(format!(
+                "Well {} has no \"{}\" column, which results format version {} requires.",
+                well.label, missing, version
+            )));
+        }
+    }
+
+    let mut timeline = Timeline::default();
+    let mut last = 0.0;
+    for record in reader.records() {
+        let record = record?;
+        let cell = |column: Option<usize>| column.and_then(|column| record.get(column)).unwrap_or_default().trim();
+        let Ok(time) = cell(Some(elapsed)).parse::<f64>() else {
+            continue;
+        };
+        last = time;
+
+        let index = cell(step).parse::<usize>().unwrap_or(1).saturating_sub(1);
+        if timeline.steps.last().map(|span| span.step) != Some(index) {
+            // Shade the step with the wavelength most of its lit wells use
+            let mut wavelengths: Vec<(Wavelength, usize)> = vec![];
+            for well in &wells {
+                let Ok(wavelength) = cell(well.wavelength).parse::<Wavelength>() else {
+                    continue;
+                };
+                if cell(well.led) != "on" {
+                    continue;
+                }
+                match wavelengths.iter_mut().find(|(w, _)| *w == wavelength) {
+                    Some((_, count)) => *count += 1,
+                    None => wavelengths.push((wavelength, 1)),
+                }
+            }
+
+            timeline.push_step(StepSpan {
+                step: index,
+                name: cell(step_name).to_string(),
+                start: time,
+                end: None,
+                wavelength: wavelengths
+                    .into_iter()
+                    .max_by_key(|(_, count)| *count)
+                    .map(|(wavelength, _)| wavelength)
+                    .unwrap_or_default(),
+                brightness: cell(brightness).parse().unwrap_or(100.0),
+                color: None,
+            });
+        }
+
+        for well in &wells {
+            let value = Some(cell(well.corrected))
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| cell(Some(well.mean)));
+            if let Ok(value) = value.parse::<f64>() {
+                timeline.record(&well.label, time, value);
+            }
+        }
+    }
+    timeline.finish(last);
+
+    Ok(timeline)
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Consecutive failures on the results file before rows are spooled elsewhere.
+const MAX_FAILURES: u32 = 3;
+
+/// Keeps the results file of a run open and queues its rows until they are synced to disk.
+pub struct ResultsWriter {
+    path: PathBuf,
+    header: String,
+    file: Option<BufWriter<File>>,
+    /// Rows not yet synced to disk, the first `written` of which are in the open file.
+    pending: Vec<String>,
+    written: usize,
+    /// Length of the file at `path` after the last successful sync.
+    synced_len: Option<u64>,
+    failures: u32,
+    last_error: Option<String>,
+    last_attempt: Instant,
+    last_sync: Instant,
+    pub sync_interval: Duration,
+    pub retry_interval: Duration,
+    warnings: Vec<String>,
+}
+
+impl ResultsWriter {
+    /// Creates a writer for the results file at `path`, writing `header` when the file is new.
+    pub fn new(path: PathBuf, header: String) -> Self {
+        let mut writer = Self {
+            path,
+            header,
+            file: None,
+            pending: vec![],
+            written: 0,
+            synced_len: None,
+            failures: 0,
+            last_error: None,
+            last_attempt: Instant::now(),
+            last_sync: Instant::now(),
+            sync_interval: Duration::from_secs(10),
+            retry_interval: Duration::from_secs(5),
+            warnings: vec![],
+        };
+        writer.open();
+        writer
+    }
+
+    /// Returns the path rows are currently written to, which changes when spooling.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Queues a row and writes every pending row if the file is available.
+    pub fn write_row(&mut self, row: String) {
+        self.pending.push(row);
+        self.drain();
+    }
+
+    /// Retries a failed file and periodically syncs the written rows to disk.
+    pub fn update(&mut self) {
+        if self.file.is_none() && self.last_attempt.elapsed() >= self.retry_interval {
+            self.open();
+            self.drain();
+        }
+
+        if self.last_sync.elapsed() >= self.sync_interval {
+            self.sync();
+        }
+    }
+
+    /// Writes every pending row and syncs the file, to be called once the run ends.
+    pub fn finish(&mut self) {
+        if self.file.is_none() {
+            self.open();
+        }
+        self.drain();
+        self.sync();
+
+        if !self.pending.is_empty() {
+            self.warnings.push(format!(
+                "{} results rows could not be written to {}.",
+                self.pending.len(),
+                self.path.display()
+            ));
+        }
+    }
+
+    /// Returns the errors encountered since the last call.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn open(&mut self) {
+        self.last_attempt = Instant::now();
+
+        let result = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .and_then(|file| {
+                // Rows written after the last sync may have partly reached the file. They are
+                // still pending, so cut them off before writing them again.
+                let mut len = file.metadata()?.len();
+                if let Some(synced) = self.synced_len.filter(|synced| *synced < len) {
+                    file.set_len(synced)?;
+                    len = synced;
+                }
+
+                let mut file = BufWriter::new(file);
+                if len == 0 {
+                    writeln!(file, "{}", self.header)?;
+                }
+                Ok((file, len))
+            });
+
+        match result {
+            Ok((file, len)) => {
+                self.file = Some(file);
+                self.synced_len = Some(len);
+            }
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn drain(&mut self) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+
+        let mut error = None;
+        for row in &self.pending[self.written..] {
+            if let Err(e) = writeln!(file, "{}", row) {
+                error = Some(e);
+                break;
+            }
+            self.written += 1;
+        }
+
+        if let Some(e) = error {
+            self.fail(e);
+        }
+    }
+
+    fn sync(&mut self) {
+        self.last_sync = Instant::now();
+
+        let Some(file) = &mut self.file else {
+            return;
+        };
+
+        let result = file
+            .flush()
+            .and_then(|_| file.get_ref().sync_data())
+            .and_then(|_| file.get_ref().metadata());
+
+        match result {
+            Ok(metadata) => {
+                self.pending.drain(..self.written);
+                self.written = 0;
+                self.synced_len = Some(metadata.len());
+                self.failures = 0;
+                self.last_error = None;
+            }
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn fail(&mut self, error: io::Error) {
+        self.file = None;
+        self.written = 0;
+        self.failures += 1;
+
+        let message = format!("Unable to write results to {}: {}", self.path.display(), error);
+        if self.last_error.as_ref() != Some(&message) {
+            self.warnings.push(message.clone());
+            self.last_error = Some(message);
+        }
+
+        if self.failures >= MAX_FAILURES {
+            if let Some(spool) = spool_path(&self.path).filter(|spool| *spool != self.path) {
+                self.warnings.push(format!(
+                    "Results are now being spooled to {}.",
+                    spool.display()
+                ));
+                self.path = spool;
+                self.synced_len = None;
+                self.failures = 0;
+                self.open();
+            }
+        }
+    }
+}
+
+/// Returns the fallback location for the results file at `path`.
+fn spool_path(path: &Path) -> Option<PathBuf> {
+    let mut spool = dirs::data_local_dir()?;
+    spool.push("tinywell");
+    spool.push("spool");
+    fs::create_dir_all(&spool).ok()?;
+    spool.push(path.file_name()?);
+    Some(spool)
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+use eframe::egui;
+use serde::{Serialize, Deserialize};
+
+/// What a well is used for in the analysis of a run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SampleRole {
+    #[default]
+    Sample,
+    Control,
+    /// Medium without cells, used as the reference of the other wells.
+    Blank,
+}
+
+impl SampleRole {
+    pub const ALL: [SampleRole; 3] = [SampleRole::Sample, SampleRole::Control, SampleRole::Blank];
+}
+
+impl fmt::Display for SampleRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SampleRole::Sample => write!(f, "Sample"),
+            SampleRole::Control => write!(f, "Control"),
+            SampleRole::Blank => write!(f, "Blank"),
+        }
+    }
+}
+
+/// Describes what was put in a well.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SampleInfo {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub strain: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub medium: String,
+    /// Density the well was inoculated at, e.g. as OD600.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inoculum_density: Option<f64>,
+    /// Wells sharing a replicate group are analysed together.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub replicate_group: String,
+    #[serde(default)]
+    pub role: SampleRole,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+}
+
+/// Samples of a sequence keyed by well label, shared by every step.
+pub type Samples = BTreeMap<String, SampleInfo>;
+
+impl SampleInfo {
+    /// Returns whether nothing was recorded about the well.
+    pub fn is_empty(&self) -> bool {
+        *self == SampleInfo::default()
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("sample_grid")
+            .num_columns(2)
+            .spacing([20.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("strain: ");
+                ui.text_edit_singleline(&mut self.strain);
+                ui.end_row();
+
+                ui.label("medium: ");
+                ui.text_edit_singleline(&mut self.medium);
+                ui.end_row();
+
+                ui.label("inoculum density: ");
+                ui.horizontal(|ui| {
+                    let mut known = self.inoculum_density.is_some();
+                    if ui.checkbox(&mut known, "").changed() {
+                        self.inoculum_density = known.then_some(0.0);
+                    }
+                    if let Some(density) = &mut self.inoculum_density {
+                        ui.add(egui::DragValue::new(density).speed(0.01).clamp_range(0.0..=f64::MAX));
+                    }
+                });
+                ui.end_row();
+
+                ui.label("replicate group: ");
+                ui.text_edit_singleline(&mut self.replicate_group);
+                ui.end_row();
+
+                ui.label("role: ");
+                egui::ComboBox::from_id_source("sample_role")
+                    .selected_text(self.role.to_string())
+                    .show_ui(ui, |ui| {
+                        for role in SampleRole::ALL {
+                            ui.selectable_value(&mut self.role, role, role.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("notes: ");
+                ui.text_edit_multiline(&mut self.notes);
+                ui.end_row();
+            });
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+use crate::ltr303::{Gain, RawChannels};
+
+/// Running statistics of every frame received for a well during one sampling window.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Aggregate {
+    sum: f64,
+    min: f32,
+    max: f32,
+    count: u32,
+}
+
+impl Aggregate {
+    /// Adds a single reading to the aggregate.
+    pub fn push(&mut self, value: f32) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+
+        self.sum += value as f64;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> Option<f32> {
+        (self.count > 0).then(|| (self.sum / self.count as f64) as f32)
+    }
+
+    pub fn min(&self) -> Option<f32> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f32> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Gain of the sensor of a well and the range events of its frames during one sampling window.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RangeFlags {
+    /// Gain of the last frame recorded with raw channels.
+    pub gain: Option<Gain>,
+    /// Number of frames with a clipped channel.
+    pub saturated: u32,
+    /// Gains the sensor was stepped from and to.
+    pub changes: Vec<(Gain, Gain)>,
+}
+
+/// Collects the frames of every well and tells the runner when a results row is due.
+pub struct Sampler {
+    pub interval: Duration,
+    window_start: Instant,
+    wells: Vec<Aggregate>,
+    /// Aggregates of the readings of every well before calibration.
+    uncalibrated: Vec<Aggregate>,
+    /// Aggregates of the raw CH0 and CH1 counts of every well.
+    channels: Vec<[Aggregate; 2]>,
+    ranges: Vec<RangeFlags>,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            window_start: Instant::now(),
+            wells: vec![],
+            uncalibrated: vec![],
+            channels: vec![],
+            ranges: vec![],
+        }
+    }
+}
+
+impl Sampler {
+    /// Records a frame for the well at index `well`.
+    pub fn record(&mut self, well: usize, value: f32) {
+        if self.wells.len() <= well {
+            self.wells.resize(well + 1, Aggregate::default());
+        }
+
+        self.wells[well].push(value);
+    }
+
+    /// Records the reading of a frame for the well at index `well` before calibration.
+    pub fn record_uncalibrated(&mut self, well: usize, value: f32) {
+        if self.uncalibrated.len() <= well {
+            self.uncalibrated.resize(well + 1, Aggregate::default());
+        }
+
+        self.uncalibrated[well].push(value);
+    }
+
+    /// Records the raw channels of a frame for the well at index `well`, measured with `gain`.
+    pub fn record_raw(&mut self, well: usize, channels: RawChannels, gain: Gain) {
+        if self.channels.len() <= well {
+            self.channels.resize(well + 1, Default::default());
+        }
+
+        self.channels[well][0].push(channels.ch0 as f32);
+        self.channels[well][1].push(channels.ch1 as f32);
+        self.range_mut(well).gain = Some(gain);
+    }
+
+    /// Flags a saturated frame of the well at index `well`.
+    pub fn record_saturated(&mut self, well: usize) {
+        self.range_mut(well).saturated += 1;
+    }
+
+    /// Records that the sensor of the well at index `well` was stepped from gain `from` to `to`.
+    pub fn record_range_change(&mut self, well: usize, from: Gain, to: Gain) {
+        if let Some(channels) = self.channels.get_mut(well) {
+            *channels = Default::default();
+        }
+        self.range_mut(well).changes.push((from, to));
+    }
+
+    fn range_mut(&mut self, well: usize) -> &mut RangeFlags {
+        if self.ranges.len() <= well {
+            self.ranges.resize(well + 1, RangeFlags::default());
+        }
+        &mut self.ranges[well]
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.window_start.elapsed() >= self.interval
+    }
+
+    /// Returns the aggregate of the well at index `well` for the current window.
+    pub fn get(&self, well: usize) -> Aggregate {
+        self.wells.get(well).copied().unwrap_or_default()
+    }
+
+    /// Returns the aggregate of the readings of the well at index `well` before calibration.
+    pub fn uncalibrated(&self, well: usize) -> Aggregate {
+        self.uncalibrated.get(well).copied().filter(|aggregate| aggregate.count() > 0).unwrap_or_else(|| self.get(well))
+    }
+
+    /// Returns the aggregates of the raw CH0 and CH1 counts of the well at index `well`.
+    pub fn channels(&self, well: usize) -> [Aggregate; 2] {
+        self.channels.get(well).copied().unwrap_or_default()
+    }
+
+    /// Returns the range flags of the well at index `well` for the current window.
+    pub fn range(&self, well: usize) -> RangeFlags {
+        self.ranges.get(well).cloned().unwrap_or_default()
+    }
+
+    /// Discards every frame collected so far and starts a new window.
+    pub fn reset(&mut self) {
+        self.wells.clear();
+        self.uncalibrated.clear();
+        self.channels.clear();
+        self.ranges.clear();
+        self.window_start = Instant::now();
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+use crate::geometry::PlateGeometry;
+
+/// Wells selected for bulk editing, kept by label across the steps of a sequence.
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    labels: BTreeSet<String>,
+    /// Position the next shift-click extends the selection from.
+    anchor: Option<(usize, usize)>,
+    /// Position a rectangle is being dragged from.
+    drag_origin: Option<(usize, usize)>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn contains(&self, label: &str) -> bool {
+        self.labels.contains(label)
+    }
+
+    /// Returns the selected labels in alphabetical order.
+    pub fn labels(&self) -> impl Iterator<Item = &String> {
+        self.labels.iter()
+    }
+
+    /// Returns the selected label when exactly one well is selected.
+    pub fn single(&self) -> Option<&String> {
+        match self.labels.len() {
+            1 => self.labels.first(),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.anchor = None;
+    }
+
+    /// Selects only the well at `row`, `col`.
+    pub fn select_only(&mut self, geometry: &PlateGeometry, row: usize, col: usize) {
+        self.clear();
+        self.toggle(geometry, row, col);
+    }
+
+    /// Adds the well at `row`, `col` to the selection or removes it when already selected.
+    pub fn toggle(&mut self, geometry: &PlateGeometry, row: usize, col: usize) {
+        if !geometry.is_populated(row, col) {
+            return;
+        }
+
+        let label = geometry.label(row, col);
+        if !self.labels.remove(&label) {
+            self.labels.insert(label);
+        }
+        self.anchor = Some((row, col));
+    }
+
+    /// Selects the usable wells between `from` and `to`, adding to the selection when `add` is set.
+    pub fn select_rectangle(
+        &mut self,
+        geometry: &PlateGeometry,
+        from: (usize, usize),
+        to: (usize, usize),
+        add: bool,
+    ) {
+        if !add {
+            self.labels.clear();
+        }
+
+        for row in from.0.min(to.0)..=from.0.max(to.0) {
+            for col in from.1.min(to.1)..=from.1.max(to.1) {
+                if geometry.is_populated(row, col) {
+                    self.labels.insert(geometry.label(row, col));
+                }
+            }
+        }
+    }
+
+    /// Selects the rectangle between the last clicked well and `row`, `col`.
+    pub fn extend_to(&mut self, geometry: &PlateGeometry, row: usize, col: usize, add: bool) {
+        let anchor = self.anchor.unwrap_or((row, col));
+        self.select_rectangle(geometry, anchor, (row, col), add);
+        self.anchor = Some(anchor);
+    }
+
+    pub fn select_row(&mut self, geometry: &PlateGeometry, row: usize, add: bool) {
+        self.select_rectangle(geometry, (row, 0), (row, geometry.columns.saturating_sub(1)), add);
+        self.anchor = Some((row, 0));
+    }
+
+    pub fn select_column(&mut self, geometry: &PlateGeometry, col: usize, add: bool) {
+        self.select_rectangle(geometry, (0, col), (geometry.rows.saturating_sub(1), col), add);
+        self.anchor = Some((0, col));
+    }
+
+    /// Starts dragging a rectangle from the well at `row`, `col`.
+    pub fn start_drag(&mut self, row: usize, col: usize) {
+        self.drag_origin = Some((row, col));
+        self.anchor = Some((row, col));
+    }
+
+    /// Selects the rectangle from where the drag started to the well at `row`, `col`.
+    pub fn drag_to(&mut self, geometry: &PlateGeometry, row: usize, col: usize, add: bool) {
+        if let Some(origin) = self.drag_origin {
+            self.select_rectangle(geometry, origin, (row, col), add);
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_origin.is_some()
+    }
+
+    pub fn stop_drag(&mut self) {
+        self.drag_origin = None;
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use eframe::egui;
+use eframe::egui::{Context, Ui};
+use serde::{Serialize, Deserialize};
+use crate::hardware::HardwareMapping;
+use crate::ltr303::{Gain, IntegrationTime, MeasurementRate, SensorConfig};
+use crate::protocol::configure_sensor;
+
+/// Settings of the light sensors used by a sequence.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorSettings {
+    #[serde(default)]
+    pub global: SensorConfig,
+    /// Settings of single sensors that differ from `global`, keyed by channel label.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub wells: BTreeMap<String, SensorConfig>,
+    /// Whether the gain of each sensor is stepped during runs to keep its counts in range.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_range: bool,
+}
+
+impl SensorSettings {
+    /// Returns whether every sensor keeps the settings the firmware starts it with.
+    pub fn is_default(&self) -> bool {
+        *self == SensorSettings::default()
+    }
+
+    /// Returns the settings of the sensor of the channel labelled `label`.
+    pub fn config_for(&self, label: &str) -> SensorConfig {
+        self.wells.get(label).copied().unwrap_or(self.global)
+    }
+
+    /// Returns the commands configuring every sensor wired in `mapping`.
+    pub fn commands(&self, mapping: &HardwareMapping) -> Vec<u8> {
+        mapping
+            .channels
+            .iter()
+            .flat_map(|channel| configure_sensor(channel.mux, channel.sensor, &self.config_for(&channel.label)))
+            .collect()
+    }
+}
+
+/// Window editing the [`SensorSettings`] of the sequence.
+#[derive(Debug, Default)]
+pub struct SensorWindow {
+    pub open: bool,
+}
+
+impl SensorWindow {
+    /// Shows the window for the sensors wired in `mapping`, returning whether `settings` changed.
+    pub fn show(&mut self, ctx: &Context, settings: &mut SensorSettings, mapping: &HardwareMapping, enabled: bool) -> bool {
+        let mut changed = false;
+
+        egui::Window::new("Sensor Settings")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(enabled, |ui| {
+                    changed = show_contents(ui, settings, mapping);
+                });
+            });
+
+        changed
+    }
+}
+
+fn show_contents(ui: &mut Ui, settings: &mut SensorSettings, mapping: &HardwareMapping) -> bool {
+    let mut changed = false;
+
+    egui::Grid::new("sensor_grid")
+        .num_columns(5)
+        .spacing([20.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            ui.label("Override");
+            ui.label("Gain");
+            ui.label("Integration Time");
+            ui.label("Measurement Rate");
+            ui.end_row();
+
+            ui.label("All Sensors");
+            ui.label("");
+            changed |= show_config(ui, "global", &mut settings.global);
+            ui.end_row();
+
+            for channel in &mapping.channels {
+                let label = &channel.label;
+                ui.label(label);
+
+                let mut overridden = settings.wells.contains_key(label);
+                if ui.checkbox(&mut overridden, "").changed() {
+                    if overridden {
+                        settings.wells.insert(label.clone(), settings.global);
+                    } else {
+                        settings.wells.remove(label);
+                    }
+                    changed = true;
+                }
+
+                match settings.wells.get_mut(label) {
+                    Some(config) => changed |= show_config(ui, label, config),
+                    None => {
+                        ui.weak(settings.global.gain.to_string());
+                        ui.weak(settings.global.integration_time.to_string());
+                        ui.weak(settings.global.measurement_rate.to_string());
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+    ui.add_space(5.0);
+    changed |= ui
+        .checkbox(&mut settings.auto_range, "Auto-Range Gain")
+        .on_hover_text("Lowers the gain of a sensor when it saturates and raises it when its counts are close to zero. Results record the gain of every well along with saturated frames and range changes.")
+        .changed();
+    ui.label("Lux is scaled by the gain and integration time of each sensor. Lower the gain when bright wells saturate and raise it or integrate longer when dim wells are noisy.");
+
+    if ui.add_enabled(!settings.wells.is_empty(), egui::Button::new("Clear Overrides")).clicked() {
+        settings.wells.clear();
+        changed = true;
+    }
+
+    changed
+}
+
+/// Shows the gain, integration time and measurement rate of `config` in three cells of a grid.
+fn show_config(ui: &mut Ui, id: &str, config: &mut SensorConfig) -> bool {
+    let before = *config;
+
+    egui::ComboBox::from_id_source(format!("{}_gain", id))
+        .selected_text(config.gain.to_string())
+        .show_ui(ui, |ui| {
+            for gain in Gain::ALL {
+                ui.selectable_value(&mut config.gain, gain, gain.to_string());
+            }
+        });
+
+    let mut integration_time = config.integration_time;
+    egui::ComboBox::from_id_source(format!("{}_integration", id))
+        .selected_text(integration_time.to_string())
+        .show_ui(ui, |ui| {
+            for option in IntegrationTime::ALL {
+                ui.selectable_value(&mut integration_time, option, option.to_string());
+            }
+        });
+    config.set_integration_time(integration_time);
+
+    egui::ComboBox::from_id_source(format!("{}_rate", id))
+        .selected_text(config.measurement_rate.to_string())
+        .show_ui(ui, |ui| {
+            // The sensor cannot measure faster than it integrates
+            for rate in MeasurementRate::ALL
+                .into_iter()
+                .filter(|rate| rate.millis() >= config.integration_time.millis())
+            {
+                ui.selectable_value(&mut config.measurement_rate, rate, rate.to_string());
+            }
+        });
+
+    *config != before
+}
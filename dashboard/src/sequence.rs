@@ -0,0 +1,121 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use crate::microplate::MicroPlate;
+use crate::sample::Samples;
+use crate::sensors::SensorSettings;
+
+/// Version of the sequence file layout written by this build.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Descriptive information stored alongside the steps of a sequence.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SequenceInfo {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub created: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub modified: Option<DateTime<Local>>,
+}
+
+/// Top-level envelope of a sequence file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceFile {
+    pub format_version: u32,
+    #[serde(flatten)]
+    pub info: SequenceInfo,
+    pub steps: Vec<MicroPlate>,
+    #[serde(default, skip_serializing_if = "Samples::is_empty")]
+    pub samples: Samples,
+    #[serde(default, skip_serializing_if = "SensorSettings::is_default")]
+    pub sensors: SensorSettings,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The file was written by a newer build of the dashboard.
+    UnsupportedVersion(u32),
+    /// The file does not look like a sequence at all.
+    UnknownLayout,
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::UnsupportedVersion(version) => write!(
+                f,
+                "format version {} is not supported, this dashboard reads up to version {}",
+                version, FORMAT_VERSION
+            ),
+            MigrationError::UnknownLayout => write!(f, "the file is not a sequence"),
+            MigrationError::Parse(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::Parse(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl SequenceFile {
+    pub fn new(info: SequenceInfo, steps: Vec<MicroPlate>) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            info,
+            steps,
+            samples: Samples::new(),
+            sensors: SensorSettings::default(),
+        }
+    }
+}
+
+/// Returns the format version of a parsed sequence file, the original bare list of steps being
+/// version `1`.
+pub fn format_version(value: &Value) -> Option<u32> {
+    match value {
+        Value::Array(_) => Some(1),
+        Value::Object(map) => map
+            .get("format_version")
+            .and_then(Value::as_u64)
+            .map(|version| version as u32),
+        _ => None,
+    }
+}
+
+/// Upgrades a parsed sequence file of any known version to the current layout.
+pub fn migrate(mut value: Value) -> Result<SequenceFile, MigrationError> {
+    let mut version = format_version(&value).ok_or(MigrationError::UnknownLayout)?;
+    if version > FORMAT_VERSION {
+        return Err(MigrationError::UnsupportedVersion(version));
+    }
+
+    while version < FORMAT_VERSION {
+        value = match version {
+            1 => migrate_v1(value),
+            _ => return Err(MigrationError::UnsupportedVersion(version)),
+        };
+        version += 1;
+    }
+
+    serde_json::from_value(value).map_err(MigrationError::Parse)
+}
+
+/// Version 1 files are a bare `Vec<MicroPlate>` without any information on the sequence.
+fn migrate_v1(value: Value) -> Value {
+    json!({
+        "format_version": 2,
+        "steps": value,
+    })
+}
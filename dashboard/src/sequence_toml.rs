@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+use crate::design::DesignSpec;
+use crate::geometry::PlateGeometry;
+use crate::microplate::MicroPlate;
+use crate::sample::Samples;
+use crate::sensors::SensorSettings;
+use crate::sequence::{SequenceFile, SequenceInfo, FORMAT_VERSION};
+use crate::wavelength::Wavelength;
+
+/// First version of the sequence format that has a TOML form.
+const FIRST_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum TextFormatError {
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    UnsupportedVersion(u32),
+    InvalidDuration(String),
+    InvalidWavelength(String),
+    UnknownWell { step: usize, label: String },
+    DisabledWell { step: usize, label: String },
+}
+
+impl fmt::Display for TextFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TextFormatError::Parse(source) => write!(f, "{}", source),
+            TextFormatError::Serialize(source) => write!(f, "{}", source),
+            TextFormatError::UnsupportedVersion(version) => write!(
+                f,
+                "format version {} is not supported, this dashboard reads versions {} to {}",
+                version, FIRST_VERSION, FORMAT_VERSION
+            ),
+            TextFormatError::InvalidDuration(duration) => write!(
+                f,
+                "\"{}\" is not a valid duration, expected h:mm:ss, m:ss or seconds",
+                duration
+            ),
+            TextFormatError::InvalidWavelength(message) => write!(f, "{}", message),
+            TextFormatError::UnknownWell { step, label } => {
+                write!(f, "step {} refers to well {} which is not on the plate", step, label)
+            }
+            TextFormatError::DisabledWell { step, label } => {
+                write!(f, "step {} configures well {} which has no sensor", step, label)
+            }
+        }
+    }
+}
+
+impl Error for TextFormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextFormatError::Parse(source) => Some(source),
+            TextFormatError::Serialize(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Values shared by every step unless the step overrides them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Defaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    brightness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wavelength: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    led_on: Option<bool>,
+}
+
+/// Settings of a single well that differ from its step.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WellOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    led_on: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wavelength: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Step {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<[u8; 3]>,
+    #[serde(flatten)]
+    settings: Defaults,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    wells: BTreeMap<String, WellOverride>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    design: Option<DesignSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Document {
+    format_version: u32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<DateTime<Local>>,
+    /// Layout shared by every step, the Tinywell board when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    geometry: Option<PlateGeometry>,
+    #[serde(default)]
+    defaults: Defaults,
+    steps: Vec<Step>,
+    #[serde(default, skip_serializing_if = "Samples::is_empty")]
+    samples: Samples,
+    /// Gain, integration time and measurement rate of the light sensors.
+    #[serde(default, skip_serializing_if = "SensorSettings::is_default")]
+    sensors: SensorSettings,
+}
+
+/// Parses a sequence written in the compact TOML format.
+pub fn from_toml(contents: &str) -> Result<SequenceFile, TextFormatError> {
+    let document: Document = toml::from_str(contents).map_err(TextFormatError::Parse)?;
+    if !(FIRST_VERSION..=FORMAT_VERSION).contains(&document.format_version) {
+        return Err(TextFormatError::UnsupportedVersion(document.format_version));
+    }
+
+    let builtin = MicroPlate::with_geometry(document.geometry.unwrap_or_default());
+    let mut steps = vec![];
+
+    for (idx, step) in document.steps.into_iter().enumerate() {
+        let settings = &step.settings;
+        let defaults = &document.defaults;
+
+        let duration = match settings.duration.as_ref().or(defaults.duration.as_ref()) {
+            Some(duration) => parse_duration(duration)?,
+            None => builtin.duration,
+        };
+        let wavelength = match settings.wavelength.as_ref().or(defaults.wavelength.as_ref()) {
+            Some(wavelength) => parse_wavelength(wavelength)?,
+            None => builtin.wavelength.clone(),
+        };
+        let brightness = settings.brightness.or(defaults.brightness).unwrap_or(builtin.brightness);
+        let led_on = settings.led_on.or(defaults.led_on).unwrap_or(true);
+
+        let mut plate = MicroPlate {
+            name: step.name,
+            color: step.color,
+            brightness,
+            wavelength: wavelength.clone(),
+            duration,
+            design: step.design,
+            ..builtin.clone()
+        };
+        for well in plate.wells.iter_mut() {
+            well.brightness = brightness;
+            well.wavelength = wavelength.clone();
+            well.led_on = led_on;
+        }
+
+        for (label, well_override) in step.wells {
+            let Some(well) = plate.wells.iter_mut().find(|well| well.label == label) else {
+                return Err(TextFormatError::UnknownWell { step: idx + 1, label });
+            };
+            if well.disabled {
+                return Err(TextFormatError::DisabledWell { step: idx + 1, label });
+            }
+
+            if let Some(led_on) = well_override.led_on {
+                well.led_on = led_on;
+            }
+            if let Some(wavelength) = &well_override.wavelength {
+                well.wavelength = parse_wavelength(wavelength)?;
+            }
+        }
+
+        steps.push(plate);
+    }
+
+    Ok(SequenceFile {
+        samples: document.samples,
+        sensors: document.sensors,
+        ..SequenceFile::new(
+            SequenceInfo {
+                name: document.name,
+                author: document.author,
+                created: document.created,
+                modified: document.modified,
+            },
+            steps,
+        )
+    })
+}
+
+/// Writes a sequence in the compact TOML format.
+pub fn to_toml(sequence: &SequenceFile) -> Result<String, TextFormatError> {
+    let steps = &sequence.steps;
+    let defaults = Defaults {
+        duration: most_common(steps.iter().map(|plate| plate.duration)).map(format_duration),
+        brightness: most_common(steps.iter().map(|plate| plate.brightness)),
+        wavelength: most_common(steps.iter().map(|plate| plate.wavelength.clone()))
+            .map(|wavelength| wavelength.to_string()),
+        led_on: most_common(
+            steps
+                .iter()
+                .flat_map(|plate| plate.wells.iter().filter(|well| !well.disabled))
+                .map(|well| well.led_on),
+        ),
+    };
+
+    let document = Document {
+        format_version: FORMAT_VERSION,
+        name: sequence.info.name.clone(),
+        author: sequence.info.author.clone(),
+        created: sequence.info.created,
+        modified: sequence.info.modified,
+        geometry: steps
+            .first()
+            .map(|plate| plate.geometry.clone())
+            .filter(|geometry| *geometry != PlateGeometry::default()),
+        steps: steps
+            .iter()
+            .map(|plate| {
+                let duration = format_duration(plate.duration);
+                let wavelength = plate.wavelength.to_string();
+                let led_on = most_common(
+                    plate.wells.iter().filter(|well| !well.disabled).map(|well| well.led_on),
+                )
+                .unwrap_or(true);
+
+                let wells = plate
+                    .wells
+                    .iter()
+                    .filter(|well| !well.disabled)
+                    .filter_map(|well| {
+                        let well_override = WellOverride {
+                            led_on: (well.led_on != led_on).then_some(well.led_on),
+                            wavelength: (well.wavelength != plate.wavelength)
+                                .then(|| well.wavelength.to_string()),
+                        };
+                        (well_override.led_on.is_some() || well_override.wavelength.is_some())
+                            .then(|| (well.label.clone(), well_override))
+                    })
+                    .collect();
+
+                Step {
+                    name: plate.name.clone(),
+                    color: plate.color,
+                    settings: Defaults {
+                        duration: (defaults.duration.as_ref() != Some(&duration)).then_some(duration),
+                        brightness: (defaults.brightness != Some(plate.brightness)).then_some(plate.brightness),
+                        wavelength: (defaults.wavelength.as_ref() != Some(&wavelength)).then_some(wavelength),
+                        led_on: (defaults.led_on != Some(led_on)).then_some(led_on),
+                    },
+                    wells,
+                    design: plate.design.clone(),
+                }
+            })
+            .collect(),
+        defaults,
+        samples: sequence.samples.clone(),
+        sensors: sequence.sensors.clone(),
+    };
+
+    toml::to_string_pretty(&document).map_err(TextFormatError::Serialize)
+}
+
+/// Parses a duration written as `h:mm:ss`, `m:ss` or plain seconds.
+pub fn parse_duration(duration: &str) -> Result<u64, TextFormatError> {
+    let invalid = || TextFormatError::InvalidDuration(duration.to_string());
+
+    let parts = duration
+        .trim()
+        .split(':')
+        .map(|part| part.trim().parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u64>, TextFormatError>>()?;
+
+    let seconds = match parts[..] {
+        [s] => Some(s),
+        [m, s] => m.checked_mul(60).and_then(|m| m.checked_add(s)),
+        [h, m, s] => h
+            .checked_mul(3600)
+            .and_then(|h| h.checked_add(m.checked_mul(60)?))
+            .and_then(|hm| hm.checked_add(s)),
+        _ => None,
+    };
+    seconds.ok_or_else(invalid)
+}
+
+/// Formats a duration in seconds as `h:mm:ss`.
+pub fn format_duration(duration: u64) -> String {
+    format!("{}:{:02}:{:02}", duration / 3600, (duration / 60) % 60, duration % 60)
+}
+
+fn parse_wavelength(wavelength: &str) -> Result<Wavelength, TextFormatError> {
+    wavelength.parse().map_err(TextFormatError::InvalidWavelength)
+}
+
+/// Returns the value appearing most often, the earliest one winning ties.
+fn most_common<T: PartialEq>(values: impl Iterator<Item = T>) -> Option<T> {
+    let mut counts: Vec<(T, usize)> = vec![];
+    for value in values {
+        match counts.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+
+    let max = counts.iter().map(|(_, count)| *count).max()?;
+    counts.into_iter().find(|(_, count)| *count == max).map(|(value, _)| value)
+}
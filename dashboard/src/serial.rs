@@ -1,8 +1,27 @@
+use crate::hardware::{Channel, HardwareMapping, SensorId};
+use crate::ltr303::{Gain, RawChannels, SensorConfig};
 use crate::microplate::MicroPlate;
+use crate::protocol::{configure_sensor, Frame};
+use crate::sensors::SensorSettings;
 use eframe::egui::{Context, Ui};
 use serialport::{available_ports, SerialPortInfo, SerialPortType};
 use std::time::Duration;
 
+/// A single sensor frame received from the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Index of the well in [`MicroPlate::wells`].
+    pub well: usize,
+    /// Sensor the frame was read from.
+    pub sensor: SensorId,
+    /// Illuminance in lux.
+    pub value: f32,
+    /// Raw channels of the sensor, sent by firmware that no longer computes the lux itself.
+    pub raw: Option<RawChannels>,
+    /// Gain the sensor reported measuring `raw` with.
+    pub gain: Option<Gain>,
+}
+
 pub struct Serial {
     ports: Vec<SerialPortInfo>,
     port_selected: usize,
@@ -32,6 +51,11 @@ impl Serial {
             _ => port.clone().port_name,
         }
     }
+    /// Returns the port currently selected in the Tools menu.
+    pub fn selected_port(&self) -> Option<&SerialPortInfo> {
+        self.ports.get(self.port_selected)
+    }
+
     pub fn show(&mut self, _ctx: &Context, ui: &mut Ui) {
         self.ports = available_ports().expect("Couldn't retrieve available ports.");
         let port_names = self
@@ -54,6 +78,20 @@ impl Serial {
     }
 
     pub fn request_led(&mut self, buf: &[u8]) {
+        self.send(buf);
+    }
+
+    /// Sets every sensor wired in `mapping` to its `settings`.
+    pub fn configure_sensors(&mut self, settings: &SensorSettings, mapping: &HardwareMapping) {
+        self.send(&settings.commands(mapping));
+    }
+
+    /// Sets `sensor` to `config`.
+    pub fn configure_sensor(&mut self, sensor: SensorId, config: &SensorConfig) {
+        self.send(&configure_sensor(sensor.mux, sensor.sensor, config));
+    }
+
+    fn send(&mut self, buf: &[u8]) {
         if let Some(info) = self.ports.get(self.port_selected) {
             if let Ok(mut port) = serialport::new(info.clone().port_name, 115_200)
                 .timeout(Duration::from_millis(10))
@@ -64,49 +102,50 @@ impl Serial {
         }
     }
 
-    pub fn request_data(&mut self, plate: &mut MicroPlate) {
+    /// Reads every pending frame from the board, storing the latest value of each well in `plate`.
+    pub fn request_data(
+        &mut self,
+        plate: &mut MicroPlate,
+        mapping: &HardwareMapping,
+        config_for: impl Fn(&Channel) -> SensorConfig,
+    ) -> Vec<Reading> {
+        let mut readings = vec![];
+
         if let Some(info) = self.ports.get(self.port_selected) {
             if let Ok(mut port) =  serialport::new(info.clone().port_name, 115_200)
                 .timeout(Duration::from_millis(10))
                 .open()
             {
-                let mut serial_buf: Vec<u8> = vec![0; 3];
-
-                while let Ok(_bytes_read) = port.read_exact(serial_buf.as_mut_slice()) {
-                    // Use the bytes in `serial_buf`
-                    let reconstructed_value: u16 =
-                        ((serial_buf[2] as u16) << 8) | (serial_buf[1] as u16);
-
-                    let mux = serial_buf[0] & 0b10000000;
-                    let idx = serial_buf[0] & 0b01111111;
-
-                    match mux {
-                        0 => match idx {
-                            0 => plate.wells[3].measurement = reconstructed_value as f32,
-                            1 => plate.wells[23].measurement = reconstructed_value as f32,
-                            2 => plate.wells[18].measurement = reconstructed_value as f32,
-                            3 => plate.wells[13].measurement = reconstructed_value as f32,
-                            4 => plate.wells[24].measurement = reconstructed_value as f32,
-                            5 => plate.wells[19].measurement = reconstructed_value as f32,
-                            6 => plate.wells[14].measurement = reconstructed_value as f32,
-                            7 => plate.wells[4].measurement = reconstructed_value as f32,
-                            _ => {}
-                        },
-                        0b10000000 => match idx {
-                            0 => plate.wells[10].measurement = reconstructed_value as f32,
-                            1 => plate.wells[6].measurement = reconstructed_value as f32,
-                            2 => plate.wells[0].measurement = reconstructed_value as f32,
-                            3 => plate.wells[20].measurement = reconstructed_value as f32,
-                            4 => plate.wells[22].measurement = reconstructed_value as f32,
-                            5 => plate.wells[17].measurement = reconstructed_value as f32,
-                            6 => plate.wells[12].measurement = reconstructed_value as f32,
-                            7 => plate.wells[2].measurement = reconstructed_value as f32,
-                            _ => {}
-                        },
-                        _ => {}
+                let mut header = [0u8; 1];
+
+                while port.read_exact(&mut header).is_ok() {
+                    let mut body = vec![0; Frame::body_length(header[0])];
+                    if port.read_exact(&mut body).is_err() {
+                        break;
                     }
+
+                    let frame = Frame::decode(header[0], &body);
+                    let (mux, idx) = frame.sensor();
+                    let sensor = SensorId { mux, sensor: idx };
+                    let (Some(channel), Some(well)) = (mapping.channel(sensor), mapping.well_for_sensor(plate, mux, idx))
+                    else {
+                        continue;
+                    };
+
+                    let (value, raw, gain) = match frame {
+                        Frame::Lux { value, .. } => (value as f32, None, None),
+                        Frame::Raw { channels, gain, .. } => {
+                            let config = config_for(channel);
+                            let config = SensorConfig { gain: gain.unwrap_or(config.gain), ..config };
+                            (config.lux(&channels) as f32, Some(channels), gain)
+                        }
+                    };
+                    plate.wells[well].measurement = value;
+                    readings.push(Reading { well, sensor, value, raw, gain });
                 }
             }
         }
+
+        readings
     }
 }
@@ -0,0 +1,260 @@
+/// Returns the natural logarithm of the gamma function of `x > 0`, using the Lanczos
+/// approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000_000_000_190_015, |sum, (idx, c)| sum + c / (x + 1.0 + idx as f64));
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// Returns the regularized incomplete beta function `I_x(a, b)`.
+fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly on this side of the mean only
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Evaluates the continued fraction of the incomplete beta function with the modified Lentz
+/// method.
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let clamp = |value: f64| if value.abs() < TINY { TINY } else { value };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut result = d;
+
+    for m in 1..300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        result *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let delta = d * c;
+        result *= delta;
+
+        if (delta - 1.0).abs() < 1e-14 {
+            break;
+        }
+    }
+    result
+}
+
+/// Returns the probability that a Student's t variable with `df` degrees of freedom is at most
+/// `t`.
+pub fn student_t_cdf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * incomplete_beta(df / (df + t * t), df / 2.0, 0.5);
+    if t >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Returns the value a Student's t variable with `df` degrees of freedom is at most with
+/// probability `p`.
+pub fn student_t_quantile(p: f64, df: f64) -> f64 {
+    if p < 0.5 {
+        return -student_t_quantile(1.0 - p, df);
+    }
+
+    // The cdf is increasing, so bisect between 0 and a bound past the quantile
+    let mut high = 1.0;
+    while student_t_cdf(high, df) < p && high < 1e12 {
+        high *= 2.0;
+    }
+    let mut low = 0.0;
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if student_t_cdf(middle, df) < p {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Returns the probability that Student's t variable with `df` degrees of freedom is further
+/// from zero than `t`.
+fn student_t_two_sided(t: f64, df: f64) -> f64 {
+    if t.is_infinite() {
+        return 0.0;
+    }
+    incomplete_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+/// Returns the probability that an F variable with `d1` and `d2` degrees of freedom is larger
+/// than `f`.
+pub fn f_survival(f: f64, d1: f64, d2: f64) -> f64 {
+    if f.is_infinite() {
+        return 0.0;
+    }
+    incomplete_beta(d2 / (d2 + d1 * f), d2 / 2.0, d1 / 2.0)
+}
+
+/// Mean and spread of a set of values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    /// Sample standard deviation, undefined for a single value.
+    pub sd: Option<f64>,
+}
+
+impl Summary {
+    /// Returns the summary of `values`, or `None` when there are none.
+    pub fn of(values: &[f64]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let n = values.len();
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let sd = (n > 1).then(|| {
+            (values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+        });
+        Some(Summary { n, mean, sd })
+    }
+
+    /// Standard error of the mean.
+    pub fn sem(&self) -> Option<f64> {
+        self.sd.map(|sd| sd / (self.n as f64).sqrt())
+    }
+
+    /// Coefficient of variation, in percent of the mean.
+    pub fn cv(&self) -> Option<f64> {
+        self.sd.filter(|_| self.mean != 0.0).map(|sd| sd / self.mean.abs() * 100.0)
+    }
+}
+
+/// Outcome of a test of the difference between the means of two groups.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TTest {
+    pub t: f64,
+    pub df: f64,
+    /// Two-sided p-value.
+    pub p: f64,
+}
+
+/// Compares the means of `a` and `b` with Welch's t-test, if both have at least two values.
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Option<TTest> {
+    let (a, b) = (Summary::of(a)?, Summary::of(b)?);
+    let (va, vb) = (a.sd?.powi(2) / a.n as f64, b.sd?.powi(2) / b.n as f64);
+    let difference = a.mean - b.mean;
+
+    if va + vb == 0.0 {
+        let t = if difference == 0.0 { 0.0 } else { f64::INFINITY.copysign(difference) };
+        let df = (a.n + b.n - 2) as f64;
+        return Some(TTest { t, df, p: if difference == 0.0 { 1.0 } else { 0.0 } });
+    }
+
+    let t = difference / (va + vb).sqrt();
+    let df = (va + vb).powi(2) / (va.powi(2) / (a.n - 1) as f64 + vb.powi(2) / (b.n - 1) as f64);
+    Some(TTest { t, df, p: student_t_two_sided(t, df) })
+}
+
+/// Difference between the means of two groups of an analysis of variance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// Indices of the groups compared.
+    pub first: usize,
+    pub second: usize,
+    /// Mean of the first group minus the mean of the second.
+    pub difference: f64,
+    /// Two-sided p-value, adjusted for the number of comparisons.
+    pub p: f64,
+}
+
+/// Outcome of a one-way analysis of variance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anova {
+    pub f: f64,
+    pub df_between: f64,
+    pub df_within: f64,
+    pub p: f64,
+    /// Every pair of groups, compared post hoc.
+    pub comparisons: Vec<Comparison>,
+}
+
+/// Tests whether the means of `groups` differ, then compares every pair with Holm-adjusted t-tests.
+pub fn one_way_anova(groups: &[Vec<f64>]) -> Option<Anova> {
+    let summaries = groups.iter().map(|group| Summary::of(group)).collect::<Option<Vec<Summary>>>()?;
+    let k = summaries.len();
+    let total: usize = summaries.iter().map(|summary| summary.n).sum();
+    if k < 2 || total <= k {
+        return None;
+    }
+
+    let grand_mean = groups.iter().flatten().sum::<f64>() / total as f64;
+    let between: f64 = summaries.iter().map(|s| s.n as f64 * (s.mean - grand_mean).powi(2)).sum();
+    let within: f64 = groups
+        .iter()
+        .zip(&summaries)
+        .map(|(group, s)| group.iter().map(|value| (value - s.mean).powi(2)).sum::<f64>())
+        .sum();
+
+    let df_between = (k - 1) as f64;
+    let df_within = (total - k) as f64;
+    let mean_square = within / df_within;
+    let f = if between == 0.0 {
+        0.0
+    } else if within == 0.0 {
+        f64::INFINITY
+    } else {
+        between / df_between / mean_square
+    };
+
+    let mut comparisons: Vec<Comparison> = (0..k)
+        .flat_map(|first| (first + 1..k).map(move |second| (first, second)))
+        .map(|(first, second)| {
+            let (a, b) = (summaries[first], summaries[second]);
+            let difference = a.mean - b.mean;
+            let se = (mean_square * (1.0 / a.n as f64 + 1.0 / b.n as f64)).sqrt();
+            let p = match (difference == 0.0, se == 0.0) {
+                (true, _) => 1.0,
+                (false, true) => 0.0,
+                (false, false) => student_t_two_sided(difference / se, df_within),
+            };
+            Comparison { first, second, difference, p }
+        })
+        .collect();
+
+    // Holm: the i-th smallest of m p-values is scaled by m - i, keeping the order
+    let mut order: Vec<usize> = (0..comparisons.len()).collect();
+    order.sort_by(|a, b| comparisons[*a].p.total_cmp(&comparisons[*b].p));
+    let m = comparisons.len();
+    let mut running = 0.0f64;
+    for (rank, idx) in order.into_iter().enumerate() {
+        running = running.max(((m - rank) as f64 * comparisons[idx].p).min(1.0));
+        comparisons[idx].p = running;
+    }
+
+    Some(Anova { f, df_between, df_within, p: f_survival(f, df_between, df_within), comparisons })
+}
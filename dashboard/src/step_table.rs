@@ -0,0 +1,329 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+use calamine::{open_workbook_auto, Data, Reader};
+use rust_xlsxwriter::{Format, Workbook};
+use crate::geometry::PlateGeometry;
+use crate::microplate::MicroPlate;
+use crate::sequence_toml::{format_duration, parse_duration};
+use crate::wavelength::Wavelength;
+
+/// Errors raised while importing or exporting a step table.
+#[derive(Debug)]
+pub enum StepTableError {
+    Csv(csv::Error),
+    Workbook(String),
+    /// The spreadsheet does not contain any sheet or row.
+    Empty,
+    /// A column required by every step is missing from the header.
+    MissingColumn(&'static str),
+    /// A cell could not be understood, at the 1-based `row` and `column` of the sheet.
+    Cell { row: usize, column: usize, header: String, message: String },
+}
+
+impl fmt::Display for StepTableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StepTableError::Csv(source) => write!(f, "{}", source),
+            StepTableError::Workbook(message) => write!(f, "{}", message),
+            StepTableError::Empty => write!(f, "The table does not contain any row."),
+            StepTableError::MissingColumn(column) => write!(f, "The table has no \"{}\" column.", column),
+            StepTableError::Cell { row, column, header, message } => write!(
+                f,
+                "Row {}, column {} ({}): {}",
+                row,
+                column_name(*column),
+                header,
+                message
+            ),
+        }
+    }
+}
+
+impl Error for StepTableError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StepTableError::Csv(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Step,
+    Name,
+    Duration,
+    Brightness,
+    Wavelength,
+    Well(usize),
+}
+
+/// Parses a step table where every row is a step laid out as `geometry`.
+pub fn parse_table(rows: &[Vec<String>], geometry: &PlateGeometry) -> Result<Vec<MicroPlate>, StepTableError> {
+    let (header, rows) = rows.split_first().ok_or(StepTableError::Empty)?;
+    let template = MicroPlate::with_geometry(geometry.clone());
+
+    let columns = header
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let normalized = name
+                .split('[')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase();
+
+            match normalized.as_str() {
+                "step" => Ok(Column::Step),
+                "name" => Ok(Column::Name),
+                "duration" => Ok(Column::Duration),
+                "brightness" => Ok(Column::Brightness),
+                "wavelength" => Ok(Column::Wavelength),
+                _ => template
+                    .wells
+                    .iter()
+                    .position(|well| !well.disabled && well.label.eq_ignore_ascii_case(name.trim()))
+                    .map(Column::Well)
+                    .ok_or_else(|| StepTableError::Cell {
+                        row: 1,
+                        column: idx + 1,
+                        header: name.clone(),
+                        message: "not a step setting nor the label of a usable well".to_string(),
+                    }),
+            }
+        })
+        .collect::<Result<Vec<Column>, StepTableError>>()?;
+
+    if !columns.contains(&Column::Duration) {
+        return Err(StepTableError::MissingColumn("Duration"));
+    }
+
+    let mut steps = vec![];
+    for (row_idx, row) in rows.iter().enumerate() {
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+
+        let cell_error = |column: usize, message: String| StepTableError::Cell {
+            row: row_idx + 2,
+            column: column + 1,
+            header: header[column].clone(),
+            message,
+        };
+        let cell = |column: usize| row.get(column).map(|cell| cell.trim()).unwrap_or_default();
+
+        let mut plate = template.clone();
+        for (idx, column) in columns.iter().enumerate() {
+            let value = cell(idx);
+            match column {
+                Column::Step => {}
+                Column::Name => plate.name = value.to_string(),
+                Column::Duration => {
+                    plate.duration = parse_duration(value)
+                        .map_err(|e| cell_error(idx, e.to_string()))?;
+                }
+                Column::Brightness if !value.is_empty() => {
+                    plate.brightness = value
+                        .trim_end_matches('%')
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|brightness| (0.0..=100.0).contains(brightness))
+                        .ok_or_else(|| cell_error(idx, format!("\"{}\" is not a brightness between 0 and 100", value)))?;
+                }
+                Column::Wavelength if !value.is_empty() => {
+                    plate.wavelength = value.parse::<Wavelength>().map_err(|e| cell_error(idx, e))?;
+                }
+                _ => {}
+            }
+        }
+
+        for well in plate.wells.iter_mut() {
+            well.brightness = plate.brightness;
+            well.wavelength = plate.wavelength.clone();
+        }
+
+        for (idx, column) in columns.iter().enumerate() {
+            if let Column::Well(well) = column {
+                let value = cell(idx);
+                let well = &mut plate.wells[*well];
+                match value.to_lowercase().as_str() {
+                    "" | "on" | "1" | "x" | "yes" | "true" => well.led_on = true,
+                    "off" | "0" | "no" | "false" => well.led_on = false,
+                    _ => {
+                        well.led_on = true;
+                        well.wavelength = value.parse::<Wavelength>().map_err(|_| {
+                            cell_error(idx, format!("\"{}\" is neither on, off nor a wavelength", value))
+                        })?;
+                    }
+                }
+            }
+        }
+
+        steps.push(plate);
+    }
+
+    if steps.is_empty() {
+        return Err(StepTableError::Empty);
+    }
+
+    Ok(steps)
+}
+
+/// Returns the step table of `sequence`, header included.
+pub fn to_table(sequence: &[MicroPlate]) -> Vec<Vec<String>> {
+    let labels: Vec<String> = sequence
+        .first()
+        .map(|plate| {
+            plate
+                .wells
+                .iter()
+                .filter(|well| !well.disabled)
+                .map(|well| well.label.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut header = vec![
+        "Step".to_string(),
+        "Name".to_string(),
+        "Duration".to_string(),
+        "Brightness [%]".to_string(),
+        "Wavelength".to_string(),
+    ];
+    header.extend(labels);
+
+    let mut table = vec![header];
+    for (idx, plate) in sequence.iter().enumerate() {
+        let mut row = vec![
+            (idx + 1).to_string(),
+            plate.name.clone(),
+            format_duration(plate.duration),
+            plate.brightness.to_string(),
+            plate.wavelength.to_string(),
+        ];
+        row.extend(plate.wells.iter().filter(|well| !well.disabled).map(|well| {
+            if !well.led_on {
+                "off".to_string()
+            } else if well.wavelength != plate.wavelength {
+                well.wavelength.to_string()
+            } else {
+                "on".to_string()
+            }
+        }));
+        table.push(row);
+    }
+
+    table
+}
+
+/// Imports the CSV or spreadsheet at `path` as steps laid out as `geometry`, reading the first
+/// sheet of workbooks.
+pub fn import(path: &Path, geometry: &PlateGeometry) -> Result<Vec<MicroPlate>, StepTableError> {
+    let rows = if is_csv(path) {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .map_err(StepTableError::Csv)?
+            .records()
+            .map(|record| record.map(|record| record.iter().map(String::from).collect()))
+            .collect::<Result<Vec<Vec<String>>, csv::Error>>()
+            .map_err(StepTableError::Csv)?
+    } else {
+        let mut workbook = open_workbook_auto(path).map_err(|e| StepTableError::Workbook(e.to_string()))?;
+        let range = workbook
+            .worksheet_range_at(0)
+            .ok_or(StepTableError::Empty)?
+            .map_err(|e| StepTableError::Workbook(e.to_string()))?;
+
+        let rows: Vec<Vec<String>> = range
+            .rows()
+            .map(|row| row.iter().map(cell_to_string).collect())
+            .collect();
+
+        // Report cells with their sheet coordinates when the table does not start at A1
+        let (start_row, start_column) = range
+            .start()
+            .map(|(row, column)| (row as usize, column as usize))
+            .unwrap_or_default();
+        return parse_table(&rows, geometry).map_err(|e| match e {
+            StepTableError::Cell { row, column, header, message } => StepTableError::Cell {
+                row: row + start_row,
+                column: column + start_column,
+                header,
+                message,
+            },
+            e => e,
+        });
+    };
+
+    parse_table(&rows, geometry)
+}
+
+/// Exports `sequence` to `path`, as a workbook when the extension is `.xlsx` and as CSV
+/// otherwise.
+pub fn export(path: &Path, sequence: &[MicroPlate]) -> Result<(), StepTableError> {
+    let table = to_table(sequence);
+
+    if is_csv(path) {
+        let mut writer = csv::Writer::from_path(path).map_err(StepTableError::Csv)?;
+        for row in &table {
+            writer.write_record(row).map_err(StepTableError::Csv)?;
+        }
+        writer.flush().map_err(|e| StepTableError::Csv(e.into()))
+    } else {
+        let xlsx_error = |e: rust_xlsxwriter::XlsxError| StepTableError::Workbook(e.to_string());
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        let bold = Format::new().set_bold();
+
+        for (row, cells) in table.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if row == 0 {
+                    worksheet
+                        .write_string_with_format(row as u32, column as u16, cell, &bold)
+                        .map_err(xlsx_error)?;
+                } else if let (3, Ok(number)) = (column, cell.parse::<f64>()) {
+                    worksheet.write_number(row as u32, column as u16, number).map_err(xlsx_error)?;
+                } else {
+                    worksheet.write_string(row as u32, column as u16, cell).map_err(xlsx_error)?;
+                }
+            }
+        }
+
+        workbook.save(path).map_err(xlsx_error)
+    }
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false)
+}
+
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::Float(value) if value.fract() == 0.0 => (*value as i64).to_string(),
+        Data::Bool(true) => "on".to_string(),
+        Data::Bool(false) => "off".to_string(),
+        // Durations typed as times are stored as a fraction of a day
+        Data::DateTime(time) => format_duration((time.as_f64() * 86400.0).round() as u64),
+        cell => cell.to_string(),
+    }
+}
+
+/// Returns the spreadsheet name of the 1-based column `column`, e.g. `27` is `AA`.
+fn column_name(mut column: usize) -> String {
+    let mut name = String::new();
+    while column > 0 {
+        let remainder = (column - 1) % 26;
+        name.insert(0, (b'A' + remainder as u8) as char);
+        column = (column - 1) / 26;
+    }
+    name
+}
@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, VecDeque};
+use crate::microplate::MicroPlate;
+use crate::wavelength::Wavelength;
+
+/// Number of most recent points kept at full resolution.
+const RECENT_POINTS: usize = 2048;
+/// Number of older points kept once decimated.
+const HISTORY_POINTS: usize = 2048;
+
+/// Readings of a single well over time, decimated so that their number stays bounded.
+#[derive(Debug, Clone)]
+pub struct Series {
+    recent: VecDeque<[f64; 2]>,
+    history: Vec<[f64; 2]>,
+    /// Number of readings averaged into each point of `history`.
+    bin: usize,
+    /// Sum and count of the readings of the bin being filled.
+    pending: ([f64; 2], usize),
+    /// First reading pushed, kept as it was once decimated.
+    first: Option<[f64; 2]>,
+}
+
+impl Default for Series {
+    fn default() -> Self {
+        Self {
+            recent: VecDeque::with_capacity(RECENT_POINTS),
+            history: vec![],
+            bin: 1,
+            pending: ([0.0, 0.0], 0),
+            first: None,
+        }
+    }
+}
+
+impl Series {
+    /// Appends the reading `value` taken `time` seconds into the run.
+    pub fn push(&mut self, time: f64, value: f64) {
+        self.first.get_or_insert([time, value]);
+        self.recent.push_back([time, value]);
+        if self.recent.len() <= RECENT_POINTS {
+            return;
+        }
+
+        let Some([time, value]) = self.recent.pop_front() else {
+            return;
+        };
+        let (sum, count) = &mut self.pending;
+        sum[0] += time;
+        sum[1] += value;
+        *count += 1;
+
+        if *count == self.bin {
+            self.history.push([sum[0] / *count as f64, sum[1] / *count as f64]);
+            self.pending = ([0.0, 0.0], 0);
+        }
+
+        if self.history.len() >= HISTORY_POINTS {
+            self.history = self
+                .history
+                .chunks(2)
+                .map(|pair| {
+                    let n = pair.len() as f64;
+                    [pair.iter().map(|p| p[0]).sum::<f64>() / n, pair.iter().map(|p| p[1]).sum::<f64>() / n]
+                })
+                .collect();
+            self.bin *= 2;
+        }
+    }
+
+    /// Returns the points of the series in chronological order.
+    pub fn points(&self) -> Vec<[f64; 2]> {
+        self.history.iter().chain(self.recent.iter()).copied().collect()
+    }
+
+    /// Returns the first reading pushed, which decimation does not average away.
+    pub fn first(&self) -> Option<[f64; 2]> {
+        self.first
+    }
+
+    /// Returns the value of the last point at or before `time`.
+    pub fn value_at(&self, time: f64) -> Option<f64> {
+        let in_recent = self.recent.partition_point(|point| point[0] <= time);
+        if in_recent > 0 {
+            return Some(self.recent[in_recent - 1][1]);
+        }
+
+        let in_history = self.history.partition_point(|point| point[0] <= time);
+        in_history.checked_sub(1).map(|idx| self.history[idx][1])
+    }
+
+    /// Returns the last reading pushed.
+    pub fn last(&self) -> Option<[f64; 2]> {
+        self.recent.back().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recent.is_empty()
+    }
+}
+
+/// Gathers the points of several series by the time they were taken, in chronological order.
+pub fn by_time<K: Ord + Clone>(series: impl IntoIterator<Item = (K, Vec<[f64; 2]>)>) -> Vec<(f64, BTreeMap<K, f64>)> {
+    let mut rows: BTreeMap<u64, (f64, BTreeMap<K, f64>)> = BTreeMap::new();
+    for (key, points) in series {
+        for [time, value] in points {
+            rows.entry(time.to_bits()).or_insert_with(|| (time, BTreeMap::new())).1.insert(key.clone(), value);
+        }
+    }
+
+    let mut rows: Vec<(f64, BTreeMap<K, f64>)> = rows.into_values().collect();
+    rows.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    rows
+}
+
+/// Time span of a step of the sequence in a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepSpan {
+    /// Index of the step in the sequence.
+    pub step: usize,
+    pub name: String,
+    pub start: f64,
+    /// End of the step, `None` while it is running.
+    pub end: Option<f64>,
+    pub wavelength: Wavelength,
+    pub brightness: f32,
+    pub color: Option<[u8; 3]>,
+}
+
+/// Readings of every well of a run, keyed by well label, and the steps they were taken in.
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    pub series: BTreeMap<String, Series>,
+    pub steps: Vec<StepSpan>,
+}
+
+impl Timeline {
+    pub fn clear(&mut self) {
+        self.series.clear();
+        self.steps.clear();
+    }
+
+    /// Ends the running step and starts the step `idx` of the sequence, `plate`, at `time`.
+    pub fn start_step(&mut self, time: f64, idx: usize, plate: &MicroPlate) {
+        self.push_step(StepSpan {
+            step: idx,
+            name: plate.display_name(idx),
+            start: time,
+            end: None,
+            wavelength: plate.wavelength.clone(),
+            brightness: plate.brightness,
+            color: plate.color,
+        });
+    }
+
+    /// Ends the running step and starts `step`.
+    pub fn push_step(&mut self, step: StepSpan) {
+        self.finish(step.start);
+        self.steps.push(step);
+    }
+
+    /// Returns the time of the last reading of any well.
+    pub fn duration(&self) -> f64 {
+        self.series
+            .values()
+            .filter_map(|series| series.last())
+            .map(|point| point[0])
+            .fold(0.0, f64::max)
+    }
+
+    /// Ends the running step at `time`.
+    pub fn finish(&mut self, time: f64) {
+        if let Some(step) = self.steps.last_mut() {
+            step.end.get_or_insert(time);
+        }
+    }
+
+    pub fn record(&mut self, label: &str, time: f64, value: f64) {
+        self.series.entry(label.to_string()).or_default().push(time, value);
+    }
+
+    /// Returns the span of the step running at `time`.
+    pub fn step_at(&self, time: f64) -> Option<&StepSpan> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| step.start <= time && step.end.map(|end| time <= end).unwrap_or(true))
+    }
+}
@@ -0,0 +1,496 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use eframe::egui;
+use crate::density::{Quantities, Quantity};
+use crate::geometry::PlateGeometry;
+use crate::groups;
+use crate::groups::{GroupedRun, Grouping};
+use crate::growth;
+use crate::growth::{GrowthFits, GrowthModel, GrowthParameter};
+use crate::heatmap::{Heatmap, HeatmapScale};
+use crate::metadata::RunMetadata;
+use crate::microplate::MicroPlate;
+use crate::plots::{show_quantity, PlotSource, PlotView};
+use crate::results;
+use crate::sample::Samples;
+use crate::selection::Selection;
+use crate::show_error;
+use crate::stats::{one_way_anova, welch_t_test, Summary};
+use crate::timeseries::StepSpan;
+
+/// A finished run read back from its results file and metadata sidecar.
+pub struct RecordedRun {
+    pub name: String,
+    pub path: PathBuf,
+    /// Readings of the run and the quantities derived from them.
+    pub quantities: Quantities,
+    /// Contents of the sidecar, when there is one next to the results file.
+    pub metadata: Option<RunMetadata>,
+    /// Why the sidecar could not be read, when it exists.
+    pub sidecar_error: Option<String>,
+    pub geometry: PlateGeometry,
+    /// Growth curves fitted to the wells, empty until requested.
+    pub fits: GrowthFits,
+}
+
+impl RecordedRun {
+    pub fn open(path: &Path) -> io::Result<RecordedRun> {
+        let timeline = results::read_timeline(&fs::read_to_string(path)?)?;
+
+        let sidecar = RunMetadata::path_for(path);
+        let (metadata, sidecar_error) = match RunMetadata::load(&sidecar) {
+            Ok(metadata) => (Some(metadata), None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (None, None),
+            Err(e) => (None, Some(format!("Unable to read {}: {}", sidecar.display(), e))),
+        };
+
+        // Runs recorded without a sidecar are shown on the first plate holding all their wells
+        let geometry = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.sequence.first())
+            .map(|plate| plate.geometry.clone())
+            .or_else(|| {
+                PlateGeometry::presets().into_iter().find(|geometry| {
+                    let plate = MicroPlate::with_geometry(geometry.clone());
+                    timeline.series.keys().all(|label| {
+                        plate.wells.iter().any(|well| !well.disabled && well.label == *label)
+                    })
+                })
+            })
+            .unwrap_or_default();
+
+        let mut run = RecordedRun {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: path.to_path_buf(),
+            quantities: Quantities { readings: timeline, ..Quantities::default() },
+            metadata,
+            sidecar_error,
+            geometry,
+            fits: GrowthFits::new(),
+        };
+        let samples = run.samples().cloned().unwrap_or_default();
+        run.quantities = Quantities::from_readings(run.quantities.readings.clone(), &samples, |span| run.plate_for(span));
+        Ok(run)
+    }
+
+    pub fn samples(&self) -> Option<&Samples> {
+        self.metadata.as_ref().map(|metadata| &metadata.samples)
+    }
+
+    pub fn grouped<'a>(&'a self, quantity: Quantity, no_samples: &'a Samples) -> GroupedRun<'a> {
+        GroupedRun {
+            timeline: self.quantities.get(quantity),
+            sequence: self.metadata.as_ref().map(|metadata| metadata.sequence.as_slice()).unwrap_or_default(),
+            samples: self.samples().unwrap_or(no_samples),
+        }
+    }
+
+    /// Returns the step that was running `time` seconds into the run.
+    pub fn plate_at(&self, time: f64) -> MicroPlate {
+        match self.quantities.readings.step_at(time) {
+            Some(span) => self.plate_for(span),
+            None => MicroPlate::with_geometry(self.geometry.clone()),
+        }
+    }
+
+    /// Returns the step of the sequence that ran during `span`.
+    fn plate_for(&self, span: &StepSpan) -> MicroPlate {
+        let recorded = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.sequence.get(span.step));
+
+        match recorded {
+            Some(plate) => plate.clone(),
+            None => {
+                let mut plate = MicroPlate {
+                    name: span.name.clone(),
+                    brightness: span.brightness,
+                    wavelength: span.wavelength.clone(),
+                    ..MicroPlate::with_geometry(self.geometry.clone())
+                };
+                plate.wells.iter_mut().for_each(|well| well.wavelength = span.wavelength.clone());
+                plate
+            }
+        }
+    }
+}
+
+/// Analysis shown under the plot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Analysis {
+    #[default]
+    GrowthFits,
+    GroupStatistics,
+}
+
+/// Value of the wells compared between groups.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    /// The reading at the cursor.
+    #[default]
+    Reading,
+    Growth(GrowthParameter),
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Variable::Reading => write!(f, "Reading at cursor"),
+            Variable::Growth(parameter) => write!(f, "{}", parameter),
+        }
+    }
+}
+
+/// Window showing runs recorded earlier, with a cursor to scrub through them.
+pub struct ResultsViewer {
+    pub open: bool,
+    runs: Vec<RecordedRun>,
+    /// Index of the run shown on the plate.
+    primary: usize,
+    /// Time shown on the plate, in seconds into the run.
+    cursor: f64,
+    plot: PlotView,
+    heatmap: Heatmap,
+    growth_model: GrowthModel,
+    /// Quantity shown on the plate and the plot, and analysed.
+    quantity: Quantity,
+    /// Quantity the growth curves of the runs were fitted to.
+    fitted: Option<Quantity>,
+    analysis: Analysis,
+    grouping: Grouping,
+    variable: Variable,
+}
+
+impl Default for ResultsViewer {
+    fn default() -> Self {
+        Self {
+            open: false,
+            runs: vec![],
+            primary: 0,
+            cursor: 0.0,
+            plot: PlotView::default(),
+            heatmap: Heatmap { enabled: true, scale: HeatmapScale::Absolute },
+            growth_model: GrowthModel::default(),
+            quantity: Quantity::default(),
+            fitted: None,
+            analysis: Analysis::default(),
+            grouping: Grouping::default(),
+            variable: Variable::default(),
+        }
+    }
+}
+
+impl ResultsViewer {
+    /// Overlays the run recorded in the results file at `path` on the runs already open.
+    pub fn add_run(&mut self, path: &Path) -> io::Result<()> {
+        let run = RecordedRun::open(path)?;
+        self.runs.retain(|open| open.path != run.path);
+        self.runs.push(run);
+        self.primary = self.runs.len() - 1;
+        self.open = true;
+        Ok(())
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Results Viewer")
+            .open(&mut open)
+            .default_size([900.0, 600.0])
+            .show(ctx, |ui| self.show_contents(ctx, ui));
+        self.open = open;
+    }
+
+    fn show_contents(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Add Run").clicked() {
+                if let Some(paths) = rfd::FileDialog::new()
+                    .add_filter("Results", &["csv"])
+                    .pick_files() {
+                    for path in paths {
+                        if let Err(e) = self.add_run(&path) {
+                            show_error("Open Results", format!("Unable to open {}:\n{}", path.display(), e));
+                        }
+                    }
+                }
+            }
+
+            let mut removed = None;
+            for (idx, run) in self.runs.iter().enumerate() {
+                ui.selectable_value(&mut self.primary, idx, &run.name)
+                    .on_hover_text(run.path.display().to_string());
+                if ui.small_button("✕").clicked() {
+                    removed = Some(idx);
+                }
+            }
+            if let Some(idx) = removed {
+                self.runs.remove(idx);
+                self.primary = self.primary.min(self.runs.len().saturating_sub(1));
+            }
+        });
+
+        let Some(run) = self.runs.get(self.primary) else {
+            ui.label("Add the results CSV of a run to view it.");
+            return;
+        };
+        if let Some(error) = &run.sidecar_error {
+            ui.colored_label(ui.visuals().warn_fg_color, error);
+        }
+
+        let duration = self.runs.iter().map(|run| run.quantities.readings.duration()).fold(0.0, f64::max);
+        ui.horizontal(|ui| {
+            ui.label("Time: ");
+            let step = run
+                .quantities
+                .readings
+                .step_at(self.cursor)
+                .map(|span| span.name.clone())
+                .unwrap_or_default();
+            ui.add(egui::Slider::new(&mut self.cursor, 0.0..=duration).suffix(" s").text(step));
+            ui.separator();
+            show_quantity(ui, "viewer", &mut self.quantity);
+            ui.radio_value(&mut self.heatmap.scale, HeatmapScale::Absolute, "Absolute");
+            ui.radio_value(&mut self.heatmap.scale, HeatmapScale::Baseline, "Relative to Baseline");
+        });
+        ui.separator();
+
+        egui::TopBottomPanel::bottom("viewer_fits")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.analysis, Analysis::GrowthFits, "Growth Fits");
+                    ui.selectable_value(&mut self.analysis, Analysis::GroupStatistics, "Group Statistics");
+                });
+                ui.separator();
+                match self.analysis {
+                    Analysis::GrowthFits => self.show_fits(ui),
+                    Analysis::GroupStatistics => self.show_statistics(ui),
+                }
+            });
+
+        let Some(run) = self.runs.get(self.primary) else {
+            return;
+        };
+        egui::SidePanel::left("viewer_plate")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                let shading = self.heatmap.shading(run.quantities.get(self.quantity), self.cursor);
+                let mut plate = run.plate_at(self.cursor);
+                ui.label(plate.display_name(
+                    run.quantities.readings.step_at(self.cursor).map(|span| span.step).unwrap_or_default(),
+                ));
+                plate.show_wells(ctx, ui, &mut Selection::default(), &shading);
+            });
+
+        let no_samples = Samples::new();
+        let sources: Vec<PlotSource> = self
+            .runs
+            .iter()
+            .map(|run| PlotSource {
+                name: &run.name,
+                timeline: run.quantities.get(self.quantity),
+                samples: run.samples().unwrap_or(&no_samples),
+                fits: (self.fitted == Some(self.quantity)).then_some(&run.fits),
+            })
+            .collect();
+        if let Some(time) = self.plot.show(ui, "viewer", &sources, Some(self.cursor)) {
+            self.cursor = time.clamp(0.0, duration);
+        }
+    }
+
+    /// Shows the growth curves fitted to the wells of the run shown on the plate.
+    fn show_fits(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Growth model: ");
+            egui::ComboBox::from_id_source("growth_model")
+                .selected_text(self.growth_model.to_string())
+                .show_ui(ui, |ui| {
+                    for model in GrowthModel::ALL {
+                        ui.selectable_value(&mut self.growth_model, model, model.to_string());
+                    }
+                });
+
+            if ui.button("Fit Growth Curves").clicked() {
+                for run in &mut self.runs {
+                    run.fits = growth::fit_timeline(self.growth_model, run.quantities.get(self.quantity));
+                }
+                self.fitted = Some(self.quantity);
+            }
+
+            let run = &self.runs[self.primary];
+            if ui.add_enabled(!run.fits.is_empty(), egui::Button::new("Export Fits")).clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name(format!("{}_fits.csv", run.name))
+                    .add_filter("CSV", &["csv"])
+                    .save_file() {
+                    if let Err(e) = growth::export(&path, &run.fits) {
+                        show_error("Export Fits", format!("Unable to export {}:\n{}", path.display(), e));
+                    }
+                }
+            }
+        });
+
+        let run = &self.runs[self.primary];
+        if run.fits.is_empty() {
+            return;
+        }
+        if let Some(quantity) = self.fitted {
+            ui.label(format!("Fitted to: {}", quantity));
+        }
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("growth_fits").striped(true).show(ui, |ui| {
+                for header in ["Well", "Lag time (h)", "μmax (1/h)", "Doubling time (h)", "Carrying capacity", "R²"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+
+                for (label, fit) in &run.fits {
+                    ui.label(label);
+                    match fit {
+                        Ok(fit) => {
+                            ui.label(fit.lag_time.to_string());
+                            ui.label(fit.max_growth_rate.to_string());
+                            ui.label(fit.doubling_time.map(|time| time.to_string()).unwrap_or_default());
+                            ui.label(format!("{:.0} ± {:.0}", fit.carrying_capacity.value, fit.carrying_capacity.margin));
+                            ui.label(format!("{:.4}", fit.r_squared));
+                        }
+                        Err(e) => {
+                            ui.colored_label(ui.visuals().warn_fg_color, e.to_string());
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Shows the statistics of the groups of wells of the run shown on the plate.
+    fn show_statistics(&mut self, ui: &mut egui::Ui) {
+        let run = &self.runs[self.primary];
+        let no_samples = Samples::new();
+        let grouped = run.grouped(self.quantity, &no_samples);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Grouping")
+                .selected_text(self.grouping.to_string())
+                .show_ui(ui, |ui| {
+                    for grouping in Grouping::ALL {
+                        ui.selectable_value(&mut self.grouping, grouping, grouping.to_string());
+                    }
+                });
+            egui::ComboBox::from_label("Value")
+                .selected_text(self.variable.to_string())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.variable, Variable::Reading, Variable::Reading.to_string());
+                    for parameter in GrowthParameter::ALL {
+                        let variable = Variable::Growth(parameter);
+                        ui.selectable_value(&mut self.variable, variable, variable.to_string());
+                    }
+                });
+
+            if ui.button("Export Statistics").clicked() {
+                let mut dialog = rfd::FileDialog::new()
+                    .set_file_name(format!("{}_statistics.xlsx", run.name))
+                    .add_filter("Excel", &["xlsx"]);
+                if let Some(directory) = run.path.parent() {
+                    dialog = dialog.set_directory(directory);
+                }
+                if let Some(path) = dialog.save_file() {
+                    if let Err(e) = groups::export(&path, &grouped, self.grouping, &run.fits) {
+                        show_error("Export Statistics", format!("Unable to export {}:\n{}", path.display(), e));
+                    }
+                }
+            }
+        });
+
+        let values = match self.variable {
+            Variable::Reading => {
+                let groups = grouped.groups_at(self.grouping, self.cursor);
+                groups::group_values(&groups, |label| {
+                    run.quantities.get(self.quantity).series.get(label).and_then(|series| series.value_at(self.cursor))
+                })
+            }
+            Variable::Growth(parameter) => {
+                if run.fits.is_empty() {
+                    ui.label("Fit growth curves to compare their parameters.");
+                    return;
+                }
+                let groups = grouped.groups(self.grouping);
+                groups::group_values(&groups, |label| groups::fitted(&run.fits, parameter, label))
+            }
+        };
+        if values.is_empty() {
+            ui.label(match self.grouping {
+                Grouping::ReplicateGroup => "No well has a replicate group.",
+                Grouping::Condition => "No well has a value to compare.",
+            });
+            return;
+        }
+
+        let format = |value: Option<f64>| value.map(|value| format!("{:.4}", value)).unwrap_or_else(|| "–".to_string());
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("group_statistics").striped(true).show(ui, |ui| {
+                for header in ["Group", "n", "Mean", "SD", "SEM", "CV (%)"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+
+                for (group, values) in &values {
+                    let Some(summary) = Summary::of(values) else {
+                        continue;
+                    };
+                    ui.label(group);
+                    ui.label(summary.n.to_string());
+                    ui.label(format(Some(summary.mean)));
+                    ui.label(format(summary.sd));
+                    ui.label(format(summary.sem()));
+                    ui.label(format(summary.cv()));
+                    ui.end_row();
+                }
+            });
+
+            let names: Vec<&String> = values.keys().collect();
+            let samples: Vec<Vec<f64>> = values.values().cloned().collect();
+            if let ([a, b], [first, second]) = (samples.as_slice(), names.as_slice()) {
+                if let Some(test) = welch_t_test(a, b) {
+                    ui.label(format!(
+                        "Welch's t-test, {} vs {}: t({:.1}) = {:.3}, p = {:.4}",
+                        first, second, test.df, test.t, test.p
+                    ));
+                }
+            }
+            match one_way_anova(&samples) {
+                Some(anova) => {
+                    ui.label(format!(
+                        "One-way ANOVA: F({}, {}) = {:.3}, p = {:.4}",
+                        anova.df_between, anova.df_within, anova.f, anova.p
+                    ));
+                    egui::CollapsingHeader::new("Pairwise comparisons (Holm-adjusted t-tests)").show(ui, |ui| {
+                        egui::Grid::new("pairwise_comparisons").striped(true).show(ui, |ui| {
+                            for header in ["Groups", "Difference", "p"] {
+                                ui.strong(header);
+                            }
+                            ui.end_row();
+                            for comparison in &anova.comparisons {
+                                ui.label(format!("{} vs {}", names[comparison.first], names[comparison.second]));
+                                ui.label(format!("{:.4}", comparison.difference));
+                                ui.label(format!("{:.4}", comparison.p));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+                None if samples.len() > 1 => {
+                    ui.label("Testing the differences needs more than one well in at least one group.");
+                }
+                None => {}
+            }
+        });
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use std::fmt::Formatter;
+use std::str::FromStr;
 use eframe::egui::Color32;
 use serde::{Serialize, Deserialize};
 
@@ -32,7 +33,28 @@ impl fmt::Display for Wavelength {
     }
 }
 
+impl FromStr for Wavelength {
+    type Err = String;
+
+    /// Parses a wavelength written as in [`fmt::Display`], e.g. `"630nm"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().replace(' ', "").to_lowercase();
+        Wavelength::ALL
+            .into_iter()
+            .find(|wavelength| wavelength.to_string() == normalized)
+            .ok_or_else(|| format!("\"{}\" is not a supported wavelength", s))
+    }
+}
+
 impl Wavelength {
+    /// Every wavelength the LED board can emit, in the order they are presented in the UI.
+    pub const ALL: [Wavelength; 4] = [
+        Wavelength::W470nm,
+        Wavelength::W570nm,
+        Wavelength::W630nm,
+        Wavelength::W850nm,
+    ];
+
     /// Converts the `Wavelength` enum variant to a corresponding `u8` value.
     ///
     /// Each wavelength variant is mapped to a unique `u8` value. This method can be used
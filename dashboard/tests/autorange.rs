@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+use chrono::Local;
+use dashboard::autorange::{AutoRanger, FrameStatus};
+use dashboard::hardware::{Channel, HardwareMapping};
+use dashboard::interleave::DarkInterleave;
+use dashboard::ltr303::{Gain, RawChannels};
+use dashboard::microplate::MicroPlate;
+use dashboard::results::{format_header, format_row};
+use dashboard::sampling::Sampler;
+use dashboard::sensors::SensorSettings;
+
+const SATURATED: RawChannels = RawChannels { ch0: 0xFFFF, ch1: 30_000 };
+const DIM: RawChannels = RawChannels { ch0: 20, ch1: 2 };
+
+fn auto_range() -> SensorSettings {
+    SensorSettings { auto_range: true, ..SensorSettings::default() }
+}
+
+fn channel(label: &str) -> Channel {
+    HardwareMapping::default().channels.into_iter().find(|channel| channel.label == label).unwrap()
+}
+
+#[test]
+fn gain_steps_down_to_the_lowest_while_saturated() {
+    let settings = auto_range();
+    let (d5, f5) = (channel("D5"), channel("F5"));
+    let mut ranger = AutoRanger::default();
+    let now = Instant::now();
+
+    let mut gains = vec![];
+    let mut gain = Gain::X96;
+    loop {
+        let check = ranger.check(&d5, &settings, SATURATED, Some(gain), now);
+        assert_eq!(check.status, FrameStatus::Saturated);
+        match check.change {
+            Some(config) => gains.push(config.gain),
+            None => break,
+        }
+        gain = ranger.config_for(&d5, &settings).gain;
+    }
+    assert_eq!(gains, vec![Gain::X48, Gain::X8, Gain::X4, Gain::X2, Gain::X1]);
+
+    // Other sensors keep their gain
+    assert_eq!(ranger.config_for(&f5, &settings).gain, Gain::X96);
+    ranger.reset();
+    assert_eq!(ranger.config_for(&d5, &settings).gain, Gain::X96);
+}
+
+#[test]
+fn gain_steps_up_on_dim_readings_once_switched() {
+    let mut settings = auto_range();
+    settings.global.gain = Gain::X4;
+    let d5 = channel("D5");
+    let mut ranger = AutoRanger::default();
+    let start = Instant::now();
+
+    let check = ranger.check(&d5, &settings, DIM, Some(Gain::X4), start);
+    assert_eq!(check.status, FrameStatus::InRange);
+    assert_eq!(check.gain, Gain::X4);
+    assert_eq!(check.change.map(|config| config.gain), Some(Gain::X8));
+    // The other settings of the sensor are kept
+    assert_eq!(check.change.unwrap().measurement_register(), settings.global.measurement_register());
+
+    // Frames measured with the previous gain are dropped however late they are checked
+    let check = ranger.check(&d5, &settings, DIM, Some(Gain::X4), start + Duration::from_millis(100));
+    assert_eq!((check.status, check.gain, check.change), (FrameStatus::Settling, Gain::X4, None));
+
+    let check = ranger.check(&d5, &settings, DIM, Some(Gain::X8), start + Duration::from_millis(200));
+    assert_eq!(check.status, FrameStatus::InRange);
+    assert_eq!(check.gain, Gain::X8);
+    assert_eq!(check.change.map(|config| config.gain), Some(Gain::X48));
+
+    // Readings in range keep the gain
+    let check = ranger.check(&d5, &settings, RawChannels { ch0: 5000, ch1: 500 }, Some(Gain::X48), start + Duration::from_secs(2));
+    assert_eq!((check.gain, check.change), (Gain::X48, None));
+}
+
+#[test]
+fn settings_are_sent_again_when_the_sensor_does_not_switch() {
+    let settings = auto_range();
+    let d5 = channel("D5");
+    let mut ranger = AutoRanger::default();
+    let start = Instant::now();
+
+    let check = ranger.check(&d5, &settings, SATURATED, Some(Gain::X96), start);
+    assert_eq!(check.change.map(|config| config.gain), Some(Gain::X48));
+
+    let check = ranger.check(&d5, &settings, SATURATED, Some(Gain::X96), start + Duration::from_millis(100));
+    assert_eq!((check.status, check.change), (FrameStatus::Settling, None));
+
+    let check = ranger.check(&d5, &settings, SATURATED, Some(Gain::X96), start + Duration::from_secs(5));
+    assert_eq!(check.status, FrameStatus::Settling);
+    assert_eq!(check.change.map(|config| config.gain), Some(Gain::X48));
+    assert_eq!(ranger.config_for(&d5, &settings).gain, Gain::X48);
+}
+
+#[test]
+fn saturation_is_flagged_without_auto_ranging() {
+    let settings = SensorSettings::default();
+    let d5 = channel("D5");
+    let mut ranger = AutoRanger::default();
+
+    let check = ranger.check(&d5, &settings, SATURATED, Some(Gain::X96), Instant::now());
+    assert_eq!(check.status, FrameStatus::Saturated);
+    assert_eq!(check.change, None);
+    // Without auto-ranging, frames are recorded with whatever gain they were measured with
+    let check = ranger.check(&d5, &settings, DIM, Some(Gain::X8), Instant::now());
+    assert_eq!((check.status, check.gain, check.change), (FrameStatus::InRange, Gain::X8, None));
+}
+
+#[test]
+fn results_rows_mark_gain_saturation_and_range_changes() {
+    let plate = MicroPlate::default();
+    let well = plate.usable_wells()[0];
+    let interleave = DarkInterleave::default();
+    let mut sampler = Sampler::default();
+    sampler.record_raw(well, RawChannels { ch0: 60_000, ch1: 6_000 }, Gain::X96);
+    sampler.record_saturated(well);
+    sampler.record_range_change(well, Gain::X96, Gain::X48);
+    sampler.record(well, 500.0);
+    sampler.record_raw(well, RawChannels { ch0: 30_000, ch1: 3_000 }, Gain::X48);
+
+    let header = format_header(&plate, &interleave, false);
+    let row = format_row(Local::now(), Duration::from_secs(1), 0, &plate, &sampler, &interleave, None);
+    let names: Vec<&str> = header.lines().nth(1).unwrap().split(',').collect();
+    let cells: Vec<&str> = row.split(',').collect();
+    assert_eq!(names.len(), cells.len());
+
+    let cell = |label: &str, column: &str| {
+        cells[names.iter().position(|name| *name == format!("{} {}", label, column)).unwrap()]
+    };
+    let label = &plate.wells[well].label;
+    assert_eq!(cell(label, "gain"), "48");
+    assert_eq!(cell(label, "saturated"), "1");
+    assert_eq!(cell(label, "range change"), "96→48");
+    // Raw counts are only averaged over frames measured with the same gain
+    assert_eq!(cell(label, "ch0"), "30000");
+
+    let other = &plate.wells[plate.usable_wells()[1]].label;
+    assert_eq!((cell(other, "gain"), cell(other, "saturated"), cell(other, "range change")), ("", "0", ""));
+}
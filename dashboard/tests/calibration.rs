@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use chrono::Local;
+use dashboard::calibration::{device_key, Calibration, CalibrationError, Capture, FORMAT_VERSION};
+use dashboard::hardware::SensorId;
+use dashboard::interleave::DarkInterleave;
+use dashboard::microplate::MicroPlate;
+use dashboard::results::{format_header, format_row};
+use dashboard::sampling::Sampler;
+use serialport::{SerialPortInfo, SerialPortType};
+
+/// Sensors of the wells D5 and F5 of the Tinywell board.
+const D5: SensorId = SensorId { mux: 1, sensor: 2 };
+const F5: SensorId = SensorId { mux: 1, sensor: 7 };
+
+/// Captures of two sensors under `references`, D5 reading true and F5 reading 20 % low with an
+/// offset of 3 lux.
+fn captures(references: &[f64]) -> Vec<Capture> {
+    references
+        .iter()
+        .map(|reference| Capture {
+            reference: *reference,
+            readings: BTreeMap::from([
+                (D5, *reference),
+                (F5, 0.8 * reference + 3.0),
+            ]),
+        })
+        .collect()
+}
+
+#[test]
+fn fits_offset_and_gain_of_every_sensor() {
+    let calibration = Calibration::fit("board", &captures(&[0.0, 50.0, 200.0])).unwrap();
+    assert_eq!(calibration.format_version, FORMAT_VERSION);
+
+    let f5 = calibration.sensor(F5).unwrap();
+    assert!((f5.gain - 1.25).abs() < 1e-9);
+    assert!((f5.offset + 3.75).abs() < 1e-9);
+    assert!((f5.r_squared.unwrap() - 1.0).abs() < 1e-9);
+    assert_eq!(f5.points.len(), 3);
+    assert!((calibration.apply(F5, 0.8 * 120.0 + 3.0) - 120.0).abs() < 1e-9);
+
+    let d5 = calibration.sensor(D5).unwrap();
+    assert!((d5.gain - 1.0).abs() < 1e-9 && d5.offset.abs() < 1e-9);
+
+    // Sensors without a calibration are left untouched
+    assert_eq!(calibration.apply(SensorId { mux: 0, sensor: 0 }, 57.0), 57.0);
+}
+
+#[test]
+fn single_condition_only_fits_the_gain() {
+    let calibration = Calibration::fit("board", &captures(&[100.0])).unwrap();
+    let f5 = calibration.sensor(F5).unwrap();
+    assert!((f5.gain - 100.0 / 83.0).abs() < 1e-9);
+    assert_eq!((f5.offset, f5.r_squared), (0.0, None));
+}
+
+#[test]
+fn reports_conditions_that_cannot_be_fitted() {
+    assert!(matches!(Calibration::fit("board", &[]), Err(CalibrationError::NoCaptures)));
+
+    let mut same = captures(&[100.0, 200.0]);
+    same[1].readings.insert(D5, 100.0);
+    assert!(matches!(Calibration::fit("board", &same), Err(CalibrationError::Degenerate(sensor)) if sensor == D5));
+    assert!(matches!(Calibration::fit("board", &captures(&[0.0])), Err(CalibrationError::Degenerate(_))));
+}
+
+#[test]
+fn references_default_to_the_mean_of_the_sensors() {
+    let capture = Capture::new(BTreeMap::from([(D5, 90.0), (F5, 110.0)]));
+    assert_eq!(capture.reference, 100.0);
+}
+
+#[test]
+fn calibration_files_round_trip_and_are_versioned() {
+    let calibration = Calibration::fit("board", &captures(&[0.0, 100.0])).unwrap();
+    let path = std::env::temp_dir()
+        .join(format!("tinywell_calibration_{}", std::process::id()))
+        .join("board.json");
+
+    calibration.save(&path).unwrap();
+    assert_eq!(Calibration::load(&path).unwrap(), calibration);
+
+    let newer = std::fs::read_to_string(&path)
+        .unwrap()
+        .replace(&format!("\"format_version\": {}", FORMAT_VERSION), &format!("\"format_version\": {}", FORMAT_VERSION + 1));
+    std::fs::write(&path, newer).unwrap();
+    let result = Calibration::load(&path);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    assert!(matches!(result, Err(CalibrationError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1));
+}
+
+#[test]
+fn device_keys_are_valid_file_names() {
+    let port = SerialPortInfo { port_name: "/dev/tty.usb modem1".to_string(), port_type: SerialPortType::Unknown };
+    assert_eq!(device_key(&port), "_dev_tty_usb_modem1");
+}
+
+#[test]
+fn results_rows_keep_calibrated_and_uncalibrated_readings() {
+    let plate = MicroPlate::default();
+    let (calibrated, uncalibrated) = (plate.usable_wells()[0], plate.usable_wells()[1]);
+    let interleave = DarkInterleave::default();
+    let mut sampler = Sampler::default();
+    sampler.record(calibrated, 120.0);
+    sampler.record_uncalibrated(calibrated, 99.0);
+    sampler.record(uncalibrated, 50.0);
+
+    let header = format_header(&plate, &interleave, false);
+    let row = format_row(Local::now(), Duration::from_secs(1), 0, &plate, &sampler, &interleave, None);
+    let names: Vec<&str> = header.lines().nth(1).unwrap().split(',').collect();
+    let cells: Vec<&str> = row.split(',').collect();
+    assert_eq!(names.len(), cells.len());
+
+    let cell = |idx: usize, column: &str| {
+        let name = format!("{} {}", plate.wells[idx].label, column);
+        cells[names.iter().position(|column| *column == name).unwrap()]
+    };
+    assert_eq!((cell(calibrated, "mean"), cell(calibrated, "uncalibrated")), ("120", "99"));
+    // Readings recorded without a calibration are their own uncalibrated readings
+    assert_eq!((cell(uncalibrated, "mean"), cell(uncalibrated, "uncalibrated")), ("50", "50"));
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+use chrono::Local;
+use dashboard::density::{optical_density, Blanks, Quantities};
+use dashboard::interleave::DarkInterleave;
+use dashboard::microplate::MicroPlate;
+use dashboard::results::{format_header, format_row, read_timeline};
+use dashboard::sample::{SampleInfo, SampleRole, Samples};
+use dashboard::sampling::Sampler;
+use dashboard::timeseries::Timeline;
+use dashboard::wavelength::Wavelength;
+
+/// A step lighting the first two usable wells with blue and the others with red, the third well
+/// being left dark, and the samples marking the first well of each colour as a blank.
+fn plate_and_samples() -> (MicroPlate, Vec<usize>, Samples) {
+    let mut plate = MicroPlate::default();
+    let wells = plate.usable_wells();
+    for (position, idx) in wells.iter().enumerate() {
+        plate.wells[*idx].led_on = position != 2;
+        plate.wells[*idx].wavelength = if position < 2 { Wavelength::W470nm } else { Wavelength::W630nm };
+    }
+
+    let mut samples = Samples::new();
+    for idx in [wells[0], wells[3]] {
+        samples.insert(plate.wells[idx].label.clone(), SampleInfo { role: SampleRole::Blank, ..SampleInfo::default() });
+    }
+    (plate, wells, samples)
+}
+
+#[test]
+fn wells_are_relative_to_blanks_of_their_wavelength() {
+    let (plate, wells, samples) = plate_and_samples();
+    let readings = vec![(wells[0], 1000.0), (wells[1], 10.0), (wells[2], 5.0), (wells[3], 400.0), (wells[4], 200.0)];
+    let blanks = Blanks::new(&plate, &samples, &readings);
+
+    assert_eq!(blanks.level(&Wavelength::W470nm), Some(1000.0));
+    assert_eq!(blanks.level(&Wavelength::W630nm), Some(400.0));
+    assert_eq!(blanks.level(&Wavelength::W850nm), None);
+    assert_eq!(blanks.transmittance(&plate, wells[1], 100.0), Some(0.1));
+    assert_eq!(blanks.transmittance(&plate, wells[2], 100.0), None);
+    assert_eq!(optical_density(0.1), 1.0);
+
+    let mut quantities = Quantities::default();
+    quantities.start_step(0.0, 0, &plate);
+    quantities.record(1.0, &plate, &blanks, &readings);
+
+    let od = |idx: usize| quantities.optical_density.series.get(&plate.wells[idx].label).and_then(|series| series.last());
+    assert_eq!(od(wells[1]), Some([1.0, 2.0]));
+    assert!((od(wells[4]).unwrap()[1] - 2f64.log10()).abs() < 1e-12);
+    assert_eq!(od(wells[0]), Some([1.0, 0.0]));
+    // Dark wells let no light of the LEDs through
+    assert_eq!(od(wells[2]), None);
+    assert_eq!(quantities.transmittance.series[&plate.wells[wells[4]].label].last(), Some([1.0, 0.5]));
+    assert_eq!(quantities.readings.series.len(), readings.len());
+}
+
+#[test]
+fn derives_quantities_of_recorded_readings() {
+    let (plate, wells, samples) = plate_and_samples();
+    let mut readings = Timeline::default();
+    readings.start_step(0.0, 0, &plate);
+    for time in [1.0, 2.0] {
+        readings.record(&plate.wells[wells[3]].label, time, 400.0);
+        readings.record(&plate.wells[wells[4]].label, time, 400.0 / (10f64).powf(time / 10.0));
+    }
+
+    let quantities = Quantities::from_readings(readings, &samples, |_| plate.clone());
+    let points = quantities.optical_density.series[&plate.wells[wells[4]].label].points();
+    assert_eq!(points.len(), 2);
+    assert!((points[0][1] - 0.1).abs() < 1e-12);
+    assert!((points[1][1] - 0.2).abs() < 1e-12);
+    assert_eq!(quantities.optical_density.steps.len(), 1);
+}
+
+#[test]
+fn results_rows_carry_transmittance_and_od() {
+    let (plate, wells, samples) = plate_and_samples();
+    let interleave = DarkInterleave::default();
+    let mut sampler = Sampler::default();
+    sampler.record(wells[3], 400.0);
+    sampler.record(wells[4], 100.0);
+
+    let readings = vec![(wells[3], 400.0), (wells[4], 100.0)];
+    let blanks = Blanks::new(&plate, &samples, &readings);
+    let header = format_header(&plate, &interleave, true);
+    let row = format_row(Local::now(), Duration::from_secs(1), 0, &plate, &sampler, &interleave, Some(&blanks));
+
+    let names: Vec<&str> = header.lines().nth(1).unwrap().split(',').collect();
+    let cells: Vec<&str> = row.split(',').collect();
+    assert_eq!(names.len(), cells.len());
+    let label = &plate.wells[wells[4]].label;
+    let od = names.iter().position(|name| *name == format!("{} OD", label)).unwrap();
+    assert_eq!(cells[od - 1], "0.25");
+    assert!((cells[od].parse::<f64>().unwrap() - 4f64.log10()).abs() < 1e-12);
+
+    // The extra columns do not get in the way of reading the run back
+    let timeline = read_timeline(&format!("{}\n{}\n", header, row)).unwrap();
+    assert_eq!(timeline.series[label].last(), Some([1.0, 100.0]));
+
+    assert!(!format_header(&plate, &interleave, false).contains(" OD"));
+}
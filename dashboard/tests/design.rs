@@ -0,0 +1,39 @@
+use dashboard::design::DesignSpec;
+use dashboard::geometry::PlateGeometry;
+use dashboard::sample::SampleRole;
+
+#[test]
+fn designs_are_reproducible_and_shared_by_every_brightness_level() {
+    let spec = DesignSpec {
+        brightness_levels: vec![25.0, 75.0],
+        dark_control: true,
+        replicates: 2,
+        seed: 42,
+        ..Default::default()
+    };
+    let first = spec.generate(&PlateGeometry::tinywell()).unwrap();
+    let second = spec.generate(&PlateGeometry::tinywell()).unwrap();
+
+    for (a, b) in first.steps[0].wells.iter().zip(second.steps[0].wells.iter()) {
+        assert_eq!(a.led_on, b.led_on);
+        assert_eq!(a.wavelength, b.wavelength);
+    }
+    for (a, b) in first.steps[0].wells.iter().zip(first.steps[1].wells.iter()) {
+        assert_eq!(a.led_on, b.led_on);
+        assert_eq!(a.wavelength, b.wavelength);
+    }
+
+    let dark = first.samples.values().filter(|sample| sample.replicate_group == "Dark");
+    assert!(dark.clone().all(|sample| sample.role == SampleRole::Control));
+    assert_eq!(dark.count(), 2);
+}
+
+#[test]
+fn leftover_wells_stay_dark() {
+    let spec = DesignSpec { replicates: 1, seed: 7, ..Default::default() };
+    let design = spec.generate(&PlateGeometry::tinywell()).unwrap();
+
+    let lit = design.steps[0].wells.iter().filter(|well| !well.disabled && well.led_on).count();
+    assert_eq!(lit, spec.treatments().len());
+    assert_eq!(design.samples.len(), spec.treatments().len());
+}
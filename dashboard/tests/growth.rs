@@ -0,0 +1,106 @@
+use dashboard::growth::{to_table, FitError, GrowthFit, GrowthFits, GrowthModel};
+use dashboard::stats::student_t_quantile;
+
+/// Readings every 5 minutes for 12 hours of a culture following `model`, starting at 200
+/// counts, with a deterministic relative noise of up to `noise`.
+fn readings(model: GrowthModel, params: &[f64], noise: f64) -> Vec<[f64; 2]> {
+    let mut state: u64 = 12345;
+    (0..144)
+        .map(|idx| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let jitter = ((state >> 33) as f64 / (1u64 << 31) as f64 - 0.5) * 2.0 * noise;
+            let hours = idx as f64 / 12.0;
+            [hours * 3600.0, 200.0 * model.evaluate(params, hours).exp() * (1.0 + jitter)]
+        })
+        .collect()
+}
+
+#[test]
+fn recovers_parameters_of_noisy_curves() {
+    for (model, params) in [
+        (GrowthModel::Logistic, vec![2.0, 0.6, 3.0]),
+        (GrowthModel::Gompertz, vec![2.0, 0.6, 3.0]),
+        (GrowthModel::Richards, vec![2.0, 0.6, 3.0, 2.0]),
+    ] {
+        let fit = GrowthFit::fit(model, &readings(model, &params, 0.01)).unwrap();
+
+        assert!(fit.lag_time.contains(3.0), "{}: lag {}", model, fit.lag_time);
+        assert!(fit.max_growth_rate.contains(0.6), "{}: rate {}", model, fit.max_growth_rate);
+        assert!(fit.lag_time.margin < 0.5, "{}: lag {}", model, fit.lag_time);
+        assert!(fit.r_squared > 0.99);
+
+        let doubling = fit.doubling_time.unwrap();
+        assert!((doubling.value - 2f64.ln() / fit.max_growth_rate.value).abs() < 1e-9);
+        let capacity = 200.0 * 2f64.exp();
+        assert!((fit.carrying_capacity.value - capacity).abs() / capacity < 0.05);
+
+        // The fitted curve follows the readings it was fitted to
+        let curve = fit.curve(2);
+        assert_eq!(curve[0][0], 0.0);
+        assert!((fit.value_at(12.0 * 3600.0) - capacity).abs() / capacity < 0.05);
+    }
+}
+
+#[test]
+fn falling_readings_have_no_doubling_time() {
+    let params = [-1.5, -0.4, 2.0];
+    let fit = GrowthFit::fit(GrowthModel::Gompertz, &readings(GrowthModel::Gompertz, &params, 0.0)).unwrap();
+
+    assert!((fit.max_growth_rate.value + 0.4).abs() < 0.01);
+    assert!((fit.lag_time.value - 2.0).abs() < 0.05);
+    assert_eq!(fit.doubling_time, None);
+}
+
+#[test]
+fn reports_why_a_well_cannot_be_fitted() {
+    let flat: Vec<[f64; 2]> = (0..100).map(|idx| [idx as f64 * 60.0, 500.0]).collect();
+    assert_eq!(GrowthFit::fit(GrowthModel::Logistic, &flat), Err(FitError::NoGrowth));
+
+    assert_eq!(GrowthFit::fit(GrowthModel::Logistic, &flat[..3]), Err(FitError::TooFewPoints));
+    assert_eq!(GrowthFit::fit(GrowthModel::Richards, &[]), Err(FitError::TooFewPoints));
+
+    let dark: Vec<[f64; 2]> = (0..100).map(|idx| [idx as f64 * 60.0, 0.0]).collect();
+    assert_eq!(GrowthFit::fit(GrowthModel::Gompertz, &dark), Err(FitError::NonPositiveBaseline));
+}
+
+#[test]
+fn table_has_a_row_per_well() {
+    let params = [2.0, 0.6, 3.0];
+    let mut fits = GrowthFits::new();
+    fits.insert("A1".to_string(), GrowthFit::fit(GrowthModel::Logistic, &readings(GrowthModel::Logistic, &params, 0.0)));
+    fits.insert("A2".to_string(), Err(FitError::NoGrowth));
+
+    let table = to_table(&fits);
+    assert_eq!(table.len(), 3);
+    assert!(table.iter().all(|row| row.len() == table[0].len()));
+    assert_eq!(table[1][0], "A1");
+    assert_eq!(table[1][1], "Logistic");
+    assert!((table[1][2].parse::<f64>().unwrap() - 3.0).abs() < 0.01);
+    assert_eq!(table[2][0], "A2");
+    assert_eq!(table[2][11], FitError::NoGrowth.to_string());
+}
+
+#[test]
+fn derived_parameters_follow_the_fit() {
+    // A culture growing from 100 to 800 counts with a lag of 2 h
+    let params = [8f64.ln(), 0.5, 2.0];
+    let points: Vec<[f64; 2]> = (0..240)
+        .map(|minute| {
+            let hours = minute as f64 / 20.0;
+            [hours * 3600.0, 100.0 * GrowthModel::Gompertz.evaluate(&params, hours).exp()]
+        })
+        .collect();
+
+    let fit = GrowthFit::fit(GrowthModel::Gompertz, &points).unwrap();
+    assert!((fit.lag_time.value - 2.0).abs() < 0.05);
+    assert!((fit.max_growth_rate.value - 0.5).abs() < 0.01);
+    assert!((fit.doubling_time.unwrap().value - 2f64.ln() / 0.5).abs() < 0.05);
+    assert!((fit.carrying_capacity.value - 800.0).abs() < 10.0);
+}
+
+#[test]
+fn confidence_intervals_use_student_t_critical_values() {
+    assert!((student_t_quantile(0.975, 10.0) - 2.228).abs() < 1e-3);
+    assert!((student_t_quantile(0.975, 1000.0) - 1.962).abs() < 1e-3);
+    assert!((student_t_quantile(0.025, 10.0) + 2.228).abs() < 1e-3);
+}
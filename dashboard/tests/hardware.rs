@@ -0,0 +1,65 @@
+use dashboard::geometry::PlateGeometry;
+use dashboard::hardware::HardwareMapping;
+use dashboard::microplate::MicroPlate;
+
+/// Well index, label, multiplexer, sensor and LED block of every wired well, as the board was
+/// wired before the mapping moved into data.
+const BOARD: [(usize, &str, u8, u8, u8); 16] = [
+    (0, "D5", 1, 2, 15),
+    (2, "F5", 1, 7, 14),
+    (3, "G5", 0, 0, 3),
+    (4, "H5", 0, 7, 2),
+    (6, "E4", 1, 1, 12),
+    (10, "D3", 1, 0, 11),
+    (12, "F3", 1, 6, 13),
+    (13, "G3", 0, 3, 0),
+    (14, "H3", 0, 6, 1),
+    (17, "F2", 1, 5, 10),
+    (18, "G2", 0, 2, 7),
+    (19, "H2", 0, 5, 6),
+    (20, "D1", 1, 3, 8),
+    (22, "F1", 1, 4, 9),
+    (23, "G1", 0, 1, 4),
+    (24, "H1", 0, 4, 5),
+];
+
+#[test]
+fn default_mapping_matches_the_board() {
+    let mapping = HardwareMapping::default();
+    let plate = MicroPlate::default();
+
+    for (idx, label, mux, sensor, led) in BOARD {
+        assert_eq!(plate.wells[idx].label, label);
+        assert!(!plate.wells[idx].disabled, "{} is disabled", label);
+
+        let channel = mapping.channel_for_well(&plate, idx).unwrap();
+        assert_eq!((channel.label.as_str(), channel.mux, channel.sensor, channel.led), (label, mux, sensor, led * 4));
+        assert_eq!(mapping.well_for_sensor(&plate, mux, sensor), Some(idx));
+    }
+
+    assert_eq!(plate.usable_wells(), BOARD.map(|(idx, ..)| idx).to_vec());
+    for idx in (0..plate.wells.len()).filter(|idx| plate.wells[*idx].disabled) {
+        assert_eq!(mapping.channel_for_well(&plate, idx), None, "{} has a channel", plate.wells[idx].label);
+    }
+}
+
+#[test]
+fn other_layouts_are_not_wired() {
+    let mapping = HardwareMapping::default();
+    let plate = MicroPlate::default();
+    assert_eq!(mapping.well_for_sensor(&plate, 0, 8), None);
+
+    // A 96-well plate also has a D5, but it sits on no sensor
+    let plate = MicroPlate::with_geometry(PlateGeometry::standard(8, 12));
+    let d5 = plate.wells.iter().position(|well| well.label == "D5").unwrap();
+    assert_eq!(mapping.well_for_sensor(&plate, 1, 2), None);
+    assert_eq!(mapping.channel_for_well(&plate, d5), None);
+}
+
+#[test]
+fn wells_are_labelled_by_their_geometry() {
+    assert_eq!(PlateGeometry::tinywell().label(0, 0), "D5");
+    assert_eq!(PlateGeometry::tinywell().label(4, 4), "H1");
+    assert_eq!(PlateGeometry::standard(8, 12).label(7, 11), "H12");
+    assert_eq!(PlateGeometry::standard(32, 48).label(26, 0), "AA1");
+}
@@ -0,0 +1,10 @@
+use dashboard::heatmap::viridis;
+use eframe::egui::Color32;
+
+#[test]
+fn viridis_is_clamped_to_its_ends() {
+    assert_eq!(viridis(0.0), Color32::from_rgb(68, 1, 84));
+    assert_eq!(viridis(1.0), Color32::from_rgb(253, 231, 37));
+    assert_eq!(viridis(2.0), viridis(1.0));
+    assert_eq!(viridis(-1.0), viridis(0.0));
+}
@@ -0,0 +1,84 @@
+use dashboard::history::{Edit, History};
+use dashboard::microplate::MicroPlate;
+use dashboard::sample::{SampleInfo, SampleRole, Samples};
+
+fn blank() -> SampleInfo {
+    SampleInfo { role: SampleRole::Blank, ..SampleInfo::default() }
+}
+
+#[test]
+fn slider_drags_are_recorded_once_released() {
+    let mut sequence = vec![MicroPlate::default()];
+    let mut samples = Samples::new();
+    let mut history = History::default();
+    history.track(&sequence, &samples, 0, false);
+
+    sequence[0].brightness = 60.0;
+    history.track(&sequence, &samples, 0, true);
+    sequence[0].brightness = 70.0;
+    history.track(&sequence, &samples, 0, true);
+    history.track(&sequence, &samples, 0, false);
+
+    history.execute(Edit::Insert { index: 1, steps: vec![MicroPlate::default()] }, &mut sequence, &mut samples);
+    assert_eq!(sequence.len(), 2);
+    assert!(history.is_dirty());
+
+    history.undo(&mut sequence, &mut samples);
+    assert_eq!(sequence.len(), 1);
+    history.undo(&mut sequence, &mut samples);
+    assert_eq!(sequence[0].brightness, 50.0);
+    assert!(!history.is_dirty());
+
+    history.redo(&mut sequence, &mut samples);
+    assert_eq!(sequence[0].brightness, 70.0);
+}
+
+#[test]
+fn sample_edits_are_undone() {
+    let mut sequence = vec![MicroPlate::default()];
+    let mut samples = Samples::new();
+    let mut history = History::default();
+    history.track(&sequence, &samples, 0, false);
+
+    // Typing notes is only recorded once the field loses focus
+    samples.insert("D5".to_string(), blank());
+    history.track(&sequence, &samples, 0, true);
+    samples.get_mut("D5").unwrap().notes = "medium".to_string();
+    history.track(&sequence, &samples, 0, true);
+    history.track(&sequence, &samples, 0, false);
+    assert!(history.is_dirty());
+
+    history.undo(&mut sequence, &mut samples);
+    assert!(samples.is_empty());
+    assert!(!history.can_undo());
+
+    history.redo(&mut sequence, &mut samples);
+    assert_eq!(samples["D5"].notes, "medium");
+}
+
+#[test]
+fn grouped_edits_are_undone_together() {
+    let mut sequence = vec![MicroPlate::default()];
+    let mut samples = Samples::from([("D5".to_string(), blank())]);
+    let mut history = History::default();
+    history.track(&sequence, &samples, 0, false);
+
+    let current = history.execute(
+        Edit::Group(vec![
+            Edit::Replace { before: sequence.clone(), after: vec![MicroPlate::default(); 3] },
+            Edit::Samples { before: samples.clone(), after: Samples::new() },
+        ]),
+        &mut sequence,
+        &mut samples,
+    );
+    assert_eq!(current, 0);
+    assert_eq!(sequence.len(), 3);
+    assert!(samples.is_empty());
+
+    // Nothing is left for the tracking to record
+    history.track(&sequence, &samples, 0, false);
+    history.undo(&mut sequence, &mut samples);
+    assert_eq!(sequence.len(), 1);
+    assert_eq!(samples["D5"], blank());
+    assert!(!history.can_undo());
+}
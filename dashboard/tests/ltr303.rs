@@ -0,0 +1,130 @@
+use std::time::Duration;
+use chrono::Local;
+use dashboard::interleave::DarkInterleave;
+use dashboard::ltr303::{
+    lux, Gain, IntegrationTime, MeasurementRate, RawChannels, SensorConfig, FIRMWARE_GAIN,
+    FIRMWARE_INTEGRATION_FACTOR,
+};
+use dashboard::microplate::MicroPlate;
+use dashboard::results::{format_header, format_row};
+use dashboard::sampling::Sampler;
+
+fn firmware_lux(ch0: u16, ch1: u16) -> f64 {
+    RawChannels { ch0, ch1 }.lux(FIRMWARE_GAIN, FIRMWARE_INTEGRATION_FACTOR)
+}
+
+#[test]
+fn lux_follows_each_range_of_the_ir_ratio() {
+    assert!((firmware_lux(9600, 0) - 177.43).abs() < 1e-9);
+    // Ratio 0.2
+    assert!((firmware_lux(800, 200) - (1.7743 * 800.0 + 1.1059 * 200.0) / 96.0).abs() < 1e-9);
+    // Ratio 0.5
+    assert!((firmware_lux(500, 500) - (4.2785 * 500.0 - 1.9548 * 500.0) / 96.0).abs() < 1e-9);
+    // Ratio 0.7
+    assert!((firmware_lux(300, 700) - (0.5926 * 300.0 + 0.1185 * 700.0) / 96.0).abs() < 1e-9);
+    // Ratio 0.9 is outside the range of the formula
+    assert_eq!(firmware_lux(100, 900), 0.0);
+    assert_eq!(firmware_lux(0, 0), 0.0);
+}
+
+#[test]
+fn ranges_switch_at_their_lower_bound() {
+    // A ratio of exactly 0.45 and 0.64 belongs to the next range
+    assert_eq!(lux(55.0, 45.0, 1.0, 1.0), 4.2785 * 55.0 - 1.9548 * 45.0);
+    assert_eq!(lux(36.0, 64.0, 1.0, 1.0), 0.5926 * 36.0 + 0.1185 * 64.0);
+    assert_eq!(lux(15.0, 85.0, 1.0, 1.0), 0.0);
+}
+
+#[test]
+fn lux_keeps_the_resolution_the_firmware_truncated() {
+    let value = firmware_lux(100, 10);
+    assert!(value > 1.0 && value < 2.0);
+    assert!((value - (1.7743 * 100.0 + 1.1059 * 10.0) / 96.0).abs() < 1e-9);
+    // Scaling with the gain and the integration factor
+    assert!((lux(100.0, 10.0, 48.0, 0.5) - 4.0 * value).abs() < 1e-9);
+}
+
+#[test]
+fn registers_encode_every_setting() {
+    // The settings of `initialize_sensor` in the firmware
+    assert_eq!(SensorConfig::default().control_register(), 0x1D);
+    assert_eq!(SensorConfig::default().measurement_register(), 0x09);
+
+    let controls: Vec<u8> = Gain::ALL
+        .iter()
+        .map(|gain| SensorConfig { gain: *gain, ..SensorConfig::default() }.control_register())
+        .collect();
+    assert_eq!(controls, vec![0x01, 0x05, 0x09, 0x0D, 0x19, 0x1D]);
+
+    let config = SensorConfig {
+        gain: Gain::X1,
+        integration_time: IntegrationTime::Ms100,
+        measurement_rate: MeasurementRate::Ms500,
+    };
+    assert_eq!(config.measurement_register(), 0b000_011);
+    let config = SensorConfig { integration_time: IntegrationTime::Ms350, measurement_rate: MeasurementRate::Ms2000, ..config };
+    assert_eq!(config.measurement_register(), 0b111_101);
+}
+
+#[test]
+fn measurement_rate_keeps_up_with_the_integration_time() {
+    let mut config = SensorConfig::default();
+    config.set_integration_time(IntegrationTime::Ms250);
+    assert_eq!(config.measurement_rate, MeasurementRate::Ms500);
+
+    // A slower rate is kept
+    config.measurement_rate = MeasurementRate::Ms2000;
+    config.set_integration_time(IntegrationTime::Ms100);
+    assert_eq!(config.measurement_rate, MeasurementRate::Ms2000);
+}
+
+#[test]
+fn lux_is_scaled_by_the_sensor_settings() {
+    let channels = RawChannels { ch0: 960, ch1: 0 };
+    let reference = SensorConfig::default();
+    assert!((reference.lux(&channels) - 17.743).abs() < 1e-9);
+
+    // The same light gives 96 times fewer counts at ×1 and twice as many in 100 ms
+    let low_gain = SensorConfig { gain: Gain::X1, ..reference };
+    assert!((low_gain.lux(&RawChannels { ch0: 10, ch1: 0 }) - 17.743).abs() < 1e-9);
+    let long = SensorConfig { integration_time: IntegrationTime::Ms100, ..reference };
+    assert!((long.lux(&RawChannels { ch0: 1920, ch1: 0 }) - 17.743).abs() < 1e-9);
+}
+
+#[test]
+fn default_settings_read_like_older_firmware() {
+    let config = SensorConfig::default();
+    for (ch0, ch1) in [(9600, 0), (800, 200), (500, 500), (300, 700), (100, 900), (0, 0), (65535, 65535)] {
+        assert_eq!(config.lux(&RawChannels { ch0, ch1 }), firmware_lux(ch0, ch1));
+    }
+}
+
+#[test]
+fn results_rows_carry_raw_channels_and_ir_ratio() {
+    let plate = MicroPlate::default();
+    let well = plate.usable_wells()[0];
+    let interleave = DarkInterleave::default();
+    let mut sampler = Sampler::default();
+    for channels in [RawChannels { ch0: 900, ch1: 100 }, RawChannels { ch0: 700, ch1: 300 }] {
+        sampler.record(well, firmware_lux(channels.ch0, channels.ch1) as f32);
+        sampler.record_raw(well, channels, Gain::X96);
+    }
+
+    let header = format_header(&plate, &interleave, false);
+    let row = format_row(Local::now(), Duration::from_secs(1), 0, &plate, &sampler, &interleave, None);
+    let names: Vec<&str> = header.lines().nth(1).unwrap().split(',').collect();
+    let cells: Vec<&str> = row.split(',').collect();
+    assert_eq!(names.len(), cells.len());
+
+    let label = &plate.wells[well].label;
+    let cell = |column: &str| cells[names.iter().position(|name| *name == format!("{} {}", label, column)).unwrap()];
+    assert_eq!(cell("ch0"), "800");
+    assert_eq!(cell("ch1"), "200");
+    assert_eq!(cell("IR ratio"), "0.2");
+    assert_eq!(cell("count"), "2");
+
+    // Frames of older firmware carry no raw channels
+    let other = &plate.wells[plate.usable_wells()[1]].label;
+    let ch0 = names.iter().position(|name| *name == format!("{} ch0", other)).unwrap();
+    assert_eq!(cells[ch0], "");
+}
@@ -0,0 +1,34 @@
+use dashboard::hardware::HardwareMapping;
+use dashboard::ltr303::{Gain, RawChannels, SensorConfig};
+use dashboard::protocol::{configure_sensor, Frame};
+use dashboard::sensors::SensorSettings;
+
+#[test]
+fn frames_carry_lux_or_raw_counts() {
+    assert_eq!(Frame::body_length(0x83), 2);
+    assert_eq!(Frame::decode(0x83, &[0x34, 0x12]), Frame::Lux { mux: 1, idx: 3, value: 0x1234 });
+
+    assert_eq!(Frame::body_length(0x7D), 4);
+    assert_eq!(
+        Frame::decode(0x7D, &[0x10, 0x27, 0xE8, 0x03]),
+        Frame::Raw { mux: 0, idx: 5, channels: RawChannels { ch0: 10_000, ch1: 1_000 }, gain: Some(Gain::X96) },
+    );
+    assert!(matches!(Frame::decode(0xD1, &[0; 4]), Frame::Raw { mux: 1, idx: 1, gain: Some(Gain::X4), .. }));
+    assert_eq!(Frame::decode(0x83, &[0, 0]).sensor(), (1, 3));
+}
+
+#[test]
+fn sensors_are_configured_with_their_own_settings() {
+    let config = SensorConfig { gain: Gain::X1, ..SensorConfig::default() };
+    assert_eq!(configure_sensor(1, 6, &config), [0xFE, 0x86, 0x01, 0x09]);
+
+    let mut settings = SensorSettings::default();
+    settings.wells.insert("D5".to_string(), SensorConfig { gain: Gain::X8, ..SensorConfig::default() });
+
+    let mapping = HardwareMapping::default();
+    let commands = settings.commands(&mapping);
+    assert_eq!(commands.len(), 4 * mapping.channels.len());
+    // D5 is the sensor 2 behind the second multiplexer
+    assert_eq!(commands[..4], [0xFE, 0x82, 0x0D, 0x09]);
+    assert_eq!(commands[4..8], [0xFE, 0x87, 0x1D, 0x09]);
+}
@@ -0,0 +1,75 @@
+use dashboard::results::{convert_legacy, read_timeline, FORMAT_VERSION, LEGACY_VERSION};
+
+const VERSION_3: &str = "# tinywell results format_version=3
+Time,Elapsed [s],Step,Step Name,Brightness [%],Step Duration [s],\
+D5 LED,D5 wavelength,D5 brightness,D5 mean,D5 min,D5 max,D5 count,D5 ch0,D5 ch1,D5 IR ratio
+2024-01-01T10:00:00+00:00,1.000,1,Blue,20,2,on,470nm,20,100,90,110,10,800,200,0.2
+";
+
+#[test]
+fn legacy_files_are_stamped_with_the_layout_they_are_converted_to() {
+    let legacy = "Brightness [%],Wavelength [nm],Duration [s],D5,F5\n50,470nm,5,12,30\n";
+    let converted = convert_legacy(legacy, None).unwrap();
+    let mut lines = converted.lines();
+
+    assert_eq!(lines.next(), Some(format!("# tinywell results format_version={}", LEGACY_VERSION).as_str()));
+    assert_eq!(LEGACY_VERSION, 2);
+    assert_eq!(
+        lines.next(),
+        Some(
+            "Time,Elapsed [s],Step,Step Name,Brightness [%],Step Duration [s],\
+             D5 LED,D5 wavelength,D5 brightness,D5 mean,D5 min,D5 max,D5 count,\
+             F5 LED,F5 wavelength,F5 brightness,F5 mean,F5 min,F5 max,F5 count"
+        )
+    );
+
+    // LED states were not recorded
+    assert_eq!(lines.next(), Some(",0.000,1,,50,5,,470nm,50,12,,,,,470nm,50,30,,,"));
+
+    let timeline = read_timeline(&converted).unwrap();
+    assert_eq!(timeline.series["F5"].points(), vec![[0.0, 30.0]]);
+}
+
+#[test]
+fn older_versions_are_read_without_the_columns_added_since() {
+    let timeline = read_timeline(VERSION_3).unwrap();
+    assert_eq!(timeline.series["D5"].points(), vec![[1.0, 100.0]]);
+
+    // A file claiming a newer version must have its columns
+    let relabelled = VERSION_3.replace("format_version=3", &format!("format_version={}", FORMAT_VERSION));
+    let error = read_timeline(&relabelled).unwrap_err();
+    assert!(error.to_string().contains(&format!("version {} requires", FORMAT_VERSION)), "{}", error);
+
+    let unsupported = VERSION_3.replace("format_version=3", &format!("format_version={}", FORMAT_VERSION + 1));
+    assert!(read_timeline(&unsupported).is_err());
+}
+
+#[test]
+fn timelines_are_read_back_by_step() {
+    let contents = "# tinywell results format_version=2
+Time,Elapsed [s],Step,Step Name,Brightness [%],Step Duration [s],D5 LED,D5 wavelength,D5 brightness,D5 mean,D5 min,D5 max,D5 count
+2024-01-01T10:00:00+00:00,1.000,1,\"Blue, dim\",20,2,on,470nm,20,100,90,110,10
+2024-01-01T10:00:01+00:00,2.000,2,Red,80,2,on,630nm,80,250,240,260,10
+2024-01-01T10:00:02+00:00,3.000,2,Red,80,2,on,630nm,80,,,,0
+";
+
+    let timeline = read_timeline(contents).unwrap();
+    assert_eq!(timeline.series["D5"].points(), vec![[1.0, 100.0], [2.0, 250.0]]);
+    assert_eq!(timeline.steps.len(), 2);
+    assert_eq!(timeline.steps[0].name, "Blue, dim");
+    assert_eq!(timeline.steps[1].wavelength.to_string(), "630nm");
+    assert_eq!(timeline.steps[1].end, Some(3.0));
+}
+
+#[test]
+fn corrected_readings_are_preferred() {
+    let contents = "# tinywell results format_version=2
+Time,Elapsed [s],Step,Step Name,Brightness [%],Step Duration [s],Blanked [s],Lit Fraction,\
+D5 LED,D5 wavelength,D5 brightness,D5 mean,D5 min,D5 max,D5 count,D5 dark,D5 corrected
+2024-01-01T10:00:00+00:00,1.000,1,Blue,20,2,0,1,on,470nm,20,100,90,110,10,,
+2024-01-01T10:00:01+00:00,2.000,1,Blue,20,2,0.5,0.75,on,470nm,20,100,90,110,10,30,70
+";
+
+    let timeline = read_timeline(contents).unwrap();
+    assert_eq!(timeline.series["D5"].points(), vec![[1.0, 100.0], [2.0, 70.0]]);
+}
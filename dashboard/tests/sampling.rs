@@ -0,0 +1,14 @@
+use dashboard::sampling::Aggregate;
+
+#[test]
+fn aggregates_track_mean_and_extremes() {
+    let mut aggregate = Aggregate::default();
+    assert_eq!(aggregate.mean(), None);
+
+    aggregate.push(2.0);
+    aggregate.push(4.0);
+    assert_eq!(aggregate.mean(), Some(3.0));
+    assert_eq!(aggregate.min(), Some(2.0));
+    assert_eq!(aggregate.max(), Some(4.0));
+    assert_eq!(aggregate.count(), 2);
+}
@@ -0,0 +1,19 @@
+use dashboard::geometry::PlateGeometry;
+use dashboard::selection::Selection;
+
+#[test]
+fn rectangles_select_the_usable_wells_they_span() {
+    let geometry = PlateGeometry::standard(8, 12);
+    let mut selection = Selection::default();
+    selection.select_rectangle(&geometry, (1, 2), (0, 0), false);
+    assert_eq!(selection.len(), 6);
+    assert!(selection.contains("B3"));
+
+    // Positions without a sensor are skipped
+    let mut selection = Selection::default();
+    selection.select_rectangle(&PlateGeometry::tinywell(), (0, 0), (1, 1), false);
+    assert_eq!(selection.labels().collect::<Vec<_>>(), ["D5", "E4"]);
+
+    selection.select_rectangle(&PlateGeometry::tinywell(), (4, 4), (4, 4), true);
+    assert_eq!(selection.labels().collect::<Vec<_>>(), ["D5", "E4", "H1"]);
+}
@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+use dashboard::config::{load_sequence, save_sequence_as, ConfigError};
+use dashboard::design::DesignSpec;
+use dashboard::geometry::PlateGeometry;
+use dashboard::ltr303::{Gain, IntegrationTime, MeasurementRate, SensorConfig};
+use dashboard::sample::{SampleInfo, SampleRole};
+use dashboard::sequence::{migrate, MigrationError, SequenceFile, FORMAT_VERSION};
+use dashboard::sequence_toml::{from_toml, parse_duration, TextFormatError};
+use dashboard::wavelength::Wavelength;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+#[test]
+fn loads_unversioned_sequence() {
+    let sequence = load_sequence(fixture("sequence_v1.json")).unwrap();
+
+    assert_eq!(sequence.format_version, FORMAT_VERSION);
+    assert_eq!(sequence.info.name, "");
+    assert_eq!(sequence.info.created, None);
+    assert_eq!(sequence.steps.len(), 2);
+    assert_eq!(sequence.steps[1].duration, 60);
+    assert_eq!(sequence.steps[1].wells.len(), 25);
+    assert!(!sequence.steps[1].wells[0].led_on);
+    assert_eq!(sequence.steps[1].name, "");
+}
+
+#[test]
+fn loads_version_2_sequence() {
+    let sequence = load_sequence(fixture("sequence_v2.json")).unwrap();
+
+    assert_eq!(sequence.format_version, 2);
+    assert_eq!(sequence.info.name, "Blue then red");
+    assert_eq!(sequence.info.author, "Tinywell");
+    assert!(sequence.info.created.is_some());
+    assert_eq!(sequence.steps.len(), 2);
+    assert_eq!(sequence.steps[0].name, "Blue");
+    assert_eq!(sequence.steps[0].wavelength.to_string(), "470nm");
+}
+
+#[test]
+fn saved_sequence_round_trips() {
+    let original = load_sequence(fixture("sequence_v1.json")).unwrap();
+
+    let path = std::env::temp_dir().join(format!("tinywell_round_trip_{}.json", std::process::id()));
+    save_sequence_as(path.clone(), &SequenceFile::new(original.info.clone(), original.steps.clone())).unwrap();
+    let loaded = load_sequence(path.clone()).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.info, original.info);
+    assert_eq!(loaded.steps.len(), original.steps.len());
+    for (loaded, original) in loaded.steps.iter().zip(original.steps.iter()) {
+        assert_eq!(loaded.duration, original.duration);
+        assert_eq!(loaded.brightness, original.brightness);
+        assert_eq!(
+            loaded.wells.iter().map(|well| well.led_on).collect::<Vec<_>>(),
+            original.wells.iter().map(|well| well.led_on).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn rejects_newer_versions() {
+    let value = serde_json::json!({ "format_version": FORMAT_VERSION + 1, "steps": [] });
+
+    assert!(matches!(
+        migrate(value),
+        Err(MigrationError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1
+    ));
+}
+
+#[test]
+fn toml_rejects_unknown_versions() {
+    for version in [1, FORMAT_VERSION + 1] {
+        let contents = format!("format_version = {}\nsteps = []\n", version);
+
+        assert!(matches!(
+            from_toml(&contents),
+            Err(TextFormatError::UnsupportedVersion(v)) if v == version
+        ));
+    }
+}
+
+#[test]
+fn reports_missing_files() {
+    assert!(matches!(
+        load_sequence(fixture("missing.json")),
+        Err(ConfigError::Io { .. })
+    ));
+}
+
+#[test]
+fn toml_sequence_round_trips() {
+    let mut original = load_sequence(fixture("sequence_v2.json")).unwrap();
+    original.samples.insert("D5".to_string(), SampleInfo {
+        strain: "MG1655".to_string(),
+        medium: "LB".to_string(),
+        inoculum_density: Some(0.05),
+        replicate_group: "A".to_string(),
+        ..Default::default()
+    });
+    original.samples.insert("H1".to_string(), SampleInfo {
+        role: SampleRole::Blank,
+        notes: "medium only".to_string(),
+        ..Default::default()
+    });
+    original.steps[1].color = Some([220, 38, 38]);
+
+    let path = std::env::temp_dir().join(format!("tinywell_round_trip_{}.toml", std::process::id()));
+    save_sequence_as(path.clone(), &original).unwrap();
+    let loaded = load_sequence(path.clone()).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.info, original.info);
+    assert_eq!(loaded.samples, original.samples);
+    assert_eq!(loaded.steps.len(), original.steps.len());
+    for (loaded, original) in loaded.steps.iter().zip(original.steps.iter()) {
+        assert_eq!(loaded.name, original.name);
+        assert_eq!(loaded.color, original.color);
+        assert_eq!(loaded.duration, original.duration);
+        assert_eq!(loaded.brightness, original.brightness);
+        assert_eq!(loaded.wavelength, original.wavelength);
+        for (loaded, original) in loaded.wells.iter().zip(original.wells.iter()) {
+            assert_eq!(loaded.label, original.label);
+            assert_eq!(loaded.disabled, original.disabled);
+            if !original.disabled {
+                assert_eq!(loaded.led_on, original.led_on);
+                assert_eq!(loaded.wavelength, original.wavelength);
+            }
+        }
+    }
+}
+
+#[test]
+fn loads_toml_fixture() {
+    let sequence = load_sequence(fixture("sequence.toml")).unwrap();
+
+    assert_eq!(sequence.info.author, "Tinywell");
+    assert_eq!(sequence.steps.len(), 2);
+    assert_eq!(sequence.steps[1].duration, 90);
+    assert!(!sequence.steps[1].wells.iter().find(|well| well.label == "D5").unwrap().led_on);
+}
+
+#[test]
+fn geometry_round_trips() {
+    let mut original = load_sequence(fixture("sequence_v2.json")).unwrap();
+    for plate in original.steps.iter_mut() {
+        plate.set_geometry(PlateGeometry::standard(8, 12));
+    }
+    original.steps[1].wells[95].led_on = false;
+
+    for extension in ["json", "toml"] {
+        let path = std::env::temp_dir().join(format!("tinywell_geometry_{}.{}", std::process::id(), extension));
+        save_sequence_as(path.clone(), &original).unwrap();
+        let loaded = load_sequence(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let plate = &loaded.steps[1];
+        assert_eq!(plate.geometry, PlateGeometry::standard(8, 12));
+        assert_eq!(plate.wells.len(), 96);
+        assert_eq!(plate.wells[95].label, "H12");
+        assert!(!plate.wells[95].led_on);
+        assert!(plate.wells.iter().all(|well| !well.disabled));
+    }
+}
+
+#[test]
+fn sensor_settings_round_trip() {
+    let mut original = load_sequence(fixture("sequence_v2.json")).unwrap();
+    assert!(original.sensors.is_default());
+    original.sensors.global.gain = Gain::X8;
+    original.sensors.wells.insert("G5".to_string(), SensorConfig {
+        gain: Gain::X1,
+        integration_time: IntegrationTime::Ms400,
+        measurement_rate: MeasurementRate::Ms500,
+    });
+    original.sensors.auto_range = true;
+
+    for extension in ["json", "toml"] {
+        let path = std::env::temp_dir().join(format!("tinywell_sensors_{}.{}", std::process::id(), extension));
+        save_sequence_as(path.clone(), &original).unwrap();
+        let loaded = load_sequence(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.sensors, original.sensors);
+        assert_eq!(loaded.sensors.config_for("D5").gain, Gain::X8);
+        assert_eq!(loaded.sensors.config_for("G5").integration_time, IntegrationTime::Ms400);
+    }
+}
+
+#[test]
+fn design_seeds_round_trip() {
+    let mut original = load_sequence(fixture("sequence_v2.json")).unwrap();
+    original.steps[0].design = Some(DesignSpec { seed: i64::MAX, ..DesignSpec::default() });
+    original.steps[1].design = Some(DesignSpec { seed: i64::MIN, ..DesignSpec::default() });
+
+    for extension in ["json", "toml"] {
+        let path = std::env::temp_dir().join(format!("tinywell_seed_{}.{}", std::process::id(), extension));
+        save_sequence_as(path.clone(), &original).unwrap();
+        let loaded = load_sequence(path.clone()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.steps[0].design, original.steps[0].design);
+        assert_eq!(loaded.steps[1].design, original.steps[1].design);
+    }
+}
+
+#[test]
+fn toml_steps_only_list_what_differs() {
+    let sequence = from_toml(r#"
+        format_version = 2
+        name = "Blue then red"
+
+        [defaults]
+        duration = "0:30:00"
+        brightness = 50
+        wavelength = "470nm"
+
+        [[steps]]
+        name = "Blue"
+
+        [[steps]]
+        name = "Red"
+        wavelength = "630nm"
+        duration = "1:00:00"
+        wells.D3 = { led_on = false }
+        wells.G5 = { wavelength = "850nm" }
+    "#).unwrap();
+
+    assert_eq!(sequence.info.name, "Blue then red");
+    assert_eq!(sequence.steps.len(), 2);
+    assert_eq!(sequence.steps[0].duration, 1800);
+    assert_eq!(sequence.steps[1].duration, 3600);
+    assert_eq!(sequence.steps[1].wavelength, Wavelength::W630nm);
+
+    let well = |label: &str| sequence.steps[1].wells.iter().find(|well| well.label == label).unwrap();
+    assert!(!well("D3").led_on);
+    assert_eq!(well("G5").wavelength, Wavelength::W850nm);
+    assert_eq!(well("F5").wavelength, Wavelength::W630nm);
+}
+
+#[test]
+fn durations_and_wavelengths_parse() {
+    assert_eq!(parse_duration("1:02:03").unwrap(), 3723);
+    assert_eq!(parse_duration("2:30").unwrap(), 150);
+    assert_eq!(parse_duration("45").unwrap(), 45);
+    assert!(parse_duration("1:xx").is_err());
+    assert!(parse_duration("5124095576030432:00:00").is_err());
+
+    assert_eq!("630nm".parse(), Ok(Wavelength::W630nm));
+    assert_eq!(" 850 nm ".parse(), Ok(Wavelength::W850nm));
+    assert!("500nm".parse::<Wavelength>().is_err());
+}
@@ -0,0 +1,143 @@
+use dashboard::groups::{parameter_table, tests_table, GroupedRun, Grouping, Groups};
+use dashboard::growth::{GrowthFit, GrowthFits, GrowthModel};
+use dashboard::microplate::MicroPlate;
+use dashboard::sample::{SampleInfo, Samples};
+use dashboard::stats::{f_survival, one_way_anova, student_t_cdf, welch_t_test, Summary};
+use dashboard::timeseries::Timeline;
+use dashboard::wavelength::Wavelength;
+
+#[test]
+fn distributions_match_reference_values() {
+    assert!((student_t_cdf(2.228, 10.0) - 0.975).abs() < 1e-4);
+    assert!((student_t_cdf(-1.0, 1.0) - 0.25).abs() < 1e-9);
+    // With 2 numerator degrees of freedom, P(F > f) = (d2 / (d2 + 2f))^(d2 / 2)
+    assert!((f_survival(3.0, 2.0, 10.0) - (10.0f64 / 16.0).powi(5)).abs() < 1e-9);
+    assert_eq!(f_survival(0.0, 2.0, 10.0), 1.0);
+}
+
+#[test]
+fn welch_t_test_matches_reference() {
+    let test = welch_t_test(&[1.0, 2.0, 3.0, 4.0, 5.0], &[2.0, 4.0, 6.0, 8.0, 10.0]).unwrap();
+    assert!((test.t + 1.8974).abs() < 1e-4);
+    assert!((test.df - 5.8824).abs() < 1e-4);
+    assert!((test.p - 0.1075).abs() < 1e-4);
+
+    assert_eq!(welch_t_test(&[1.0], &[2.0, 3.0]), None);
+    assert_eq!(welch_t_test(&[1.0, 1.0], &[1.0, 1.0]).unwrap().p, 1.0);
+}
+
+#[test]
+fn post_hoc_comparisons_are_holm_adjusted() {
+    let anova = one_way_anova(&[vec![1.0, 2.0, 3.0], vec![2.0, 3.0, 4.0], vec![5.0, 6.0, 7.0]]).unwrap();
+    assert!((anova.f - 13.0).abs() < 1e-9);
+    assert!((anova.p - 0.1875f64.powi(3)).abs() < 1e-9);
+    assert_eq!((anova.df_between, anova.df_within), (2.0, 6.0));
+
+    let pairs: Vec<(usize, usize, f64)> = anova
+        .comparisons
+        .iter()
+        .map(|comparison| (comparison.first, comparison.second, comparison.p))
+        .collect();
+    assert_eq!(pairs.iter().map(|(a, b, _)| (*a, *b)).collect::<Vec<_>>(), vec![(0, 1), (0, 2), (1, 2)]);
+    // Raw p-values of 0.267, 0.0027 and 0.0104 scaled by 1, 3 and 2
+    assert!((pairs[0].2 - 0.2666).abs() < 1e-3);
+    assert!((pairs[1].2 - 0.0081).abs() < 1e-3);
+    assert!((pairs[2].2 - 0.0208).abs() < 1e-3);
+
+    assert_eq!(one_way_anova(&[vec![1.0, 2.0]]), None);
+    assert_eq!(one_way_anova(&[vec![1.0], vec![2.0]]), None);
+}
+
+#[test]
+fn wells_are_grouped_by_condition_and_replicate_group() {
+    let mut first = MicroPlate::default();
+    let labels: Vec<String> = first.usable_wells().iter().take(4).map(|idx| first.wells[*idx].label.clone()).collect();
+    first.brightness = 40.0;
+    for well in first.wells.iter_mut() {
+        well.wavelength = Wavelength::W630nm;
+    }
+    first.wells.iter_mut().find(|well| well.label == labels[0]).unwrap().led_on = false;
+    let mut second = first.clone();
+    second.wells.iter_mut().find(|well| well.label == labels[1]).unwrap().led_on = false;
+    let sequence = vec![first.clone(), second.clone()];
+
+    let mut timeline = Timeline::default();
+    timeline.start_step(0.0, 0, &first);
+    for (idx, label) in labels.iter().enumerate() {
+        timeline.record(label, 0.0, 100.0 + idx as f64);
+        timeline.record(label, 10.0, 200.0 + idx as f64);
+    }
+    timeline.start_step(10.0, 1, &second);
+
+    let mut samples = Samples::new();
+    for label in &labels[..2] {
+        samples.insert(label.clone(), SampleInfo { replicate_group: "WT".to_string(), ..SampleInfo::default() });
+    }
+    let run = GroupedRun { timeline: &timeline, sequence: &sequence, samples: &samples };
+
+    let red_light = format!("{} 40%", Wavelength::W630nm);
+    let at_start = run.groups_at(Grouping::Condition, 5.0);
+    assert_eq!(at_start["Dark"], vec![labels[0].clone()]);
+    assert_eq!(at_start[&red_light].len(), 3);
+
+    let over_run = run.groups(Grouping::Condition);
+    assert_eq!(over_run[&format!("{} → Dark", red_light)], vec![labels[1].clone()]);
+    assert_eq!(over_run[&red_light].len(), 2);
+
+    let replicates = run.groups(Grouping::ReplicateGroup);
+    assert_eq!(replicates.len(), 1);
+    assert_eq!(replicates["WT"].len(), 2);
+
+    let summaries = run.summaries_over_time(Grouping::ReplicateGroup);
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[1].2.mean, 200.5);
+}
+
+#[test]
+fn growth_parameters_are_summarised_and_tested_per_group() {
+    let curve = |rate: f64| -> Vec<[f64; 2]> {
+        (0..100)
+            .map(|idx| {
+                let hours = idx as f64 / 10.0;
+                [hours * 3600.0, 100.0 * GrowthModel::Logistic.evaluate(&[2.0, rate, 2.0], hours).exp()]
+            })
+            .collect()
+    };
+
+    let mut fits = GrowthFits::new();
+    let mut groups = Groups::new();
+    for (group, rates) in [("slow", [0.40, 0.42, 0.44]), ("fast", [0.80, 0.82, 0.84])] {
+        for (idx, rate) in rates.iter().enumerate() {
+            let label = format!("{}{}", group, idx);
+            fits.insert(label.clone(), GrowthFit::fit(GrowthModel::Logistic, &curve(*rate)));
+            groups.entry(group.to_string()).or_default().push(label);
+        }
+    }
+
+    let summaries = parameter_table(&groups, &fits);
+    let rate = summaries
+        .iter()
+        .find(|row| row[0] == "Max specific growth rate (1/h)" && row[1] == "slow")
+        .unwrap();
+    assert_eq!(rate[2], "3");
+    assert!((rate[3].parse::<f64>().unwrap() - 0.42).abs() < 1e-3);
+
+    let tests = tests_table(&groups, &fits);
+    let welch = tests
+        .iter()
+        .find(|row| row[0] == "Max specific growth rate (1/h)" && row[1] == "Welch's t-test")
+        .unwrap();
+    assert_eq!(welch[2], "fast vs slow");
+    assert!(welch[5].parse::<f64>().unwrap() < 0.001);
+    assert!(tests.iter().any(|row| row[1] == "One-way ANOVA"));
+}
+
+#[test]
+fn summaries_need_two_values_for_a_spread() {
+    let summary = Summary::of(&[2.0, 4.0, 6.0]).unwrap();
+    assert_eq!(summary.mean, 4.0);
+    assert_eq!(summary.sd, Some(2.0));
+    assert_eq!(summary.cv(), Some(50.0));
+    assert_eq!(Summary::of(&[1.0]).unwrap().sem(), None);
+    assert_eq!(Summary::of(&[]), None);
+}
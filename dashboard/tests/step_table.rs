@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+use dashboard::config::load_sequence;
+use dashboard::geometry::PlateGeometry;
+use dashboard::step_table::{export, import, parse_table};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+#[test]
+fn step_table_round_trips() {
+    let original = load_sequence(fixture("sequence_v2.json")).unwrap().steps;
+
+    for extension in ["csv", "xlsx"] {
+        let path = std::env::temp_dir().join(format!("tinywell_steps_{}.{}", std::process::id(), extension));
+        export(&path, &original).unwrap();
+        let imported = import(&path, &PlateGeometry::tinywell()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(imported.len(), original.len());
+        for (imported, original) in imported.iter().zip(original.iter()) {
+            assert_eq!(imported.name, original.name);
+            assert_eq!(imported.duration, original.duration);
+            assert_eq!(imported.brightness, original.brightness);
+            assert_eq!(imported.wavelength, original.wavelength);
+            for (imported, original) in imported.wells.iter().zip(original.wells.iter()) {
+                if !original.disabled {
+                    assert_eq!(imported.led_on, original.led_on, "{} in {}", original.label, extension);
+                    assert_eq!(imported.wavelength, original.wavelength);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn reports_invalid_cells_by_row_and_column() {
+    let path = std::env::temp_dir().join(format!("tinywell_invalid_steps_{}.csv", std::process::id()));
+    std::fs::write(&path, "Duration,Brightness [%],D5\n0:10:00,50,on\n0:10:00,150,off\n").unwrap();
+    let error = import(&path, &PlateGeometry::tinywell()).unwrap_err();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        error.to_string(),
+        "Row 3, column B (Brightness [%]): \"150\" is not a brightness between 0 and 100"
+    );
+}
+
+#[test]
+fn well_cells_switch_or_recolour_their_led() {
+    let table = vec![
+        vec!["Duration", "Brightness [%]", "Wavelength", "D5", "F5"],
+        vec!["0:10:00", "50", "470nm", "on", "off"],
+        vec!["30", "80", "630nm", "850nm", ""],
+    ];
+    let table: Vec<Vec<String>> = table
+        .into_iter()
+        .map(|row| row.into_iter().map(String::from).collect())
+        .collect();
+
+    let geometry = PlateGeometry::tinywell();
+    let steps = parse_table(&table, &geometry).unwrap();
+    assert_eq!(steps[0].duration, 600);
+    assert!(!steps[0].wells[2].led_on);
+    assert_eq!(steps[1].wells[0].wavelength.to_string(), "850nm");
+    // An empty cell keeps the LED on at the step wavelength
+    assert!(steps[1].wells[2].led_on);
+    assert_eq!(steps[1].wells[2].wavelength.to_string(), "630nm");
+
+    let mut invalid = table.clone();
+    invalid[2][3] = "maybe".to_string();
+    assert_eq!(
+        parse_table(&invalid, &geometry).unwrap_err().to_string(),
+        "Row 3, column D (D5): \"maybe\" is neither on, off nor a wavelength"
+    );
+}
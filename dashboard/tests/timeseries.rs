@@ -0,0 +1,25 @@
+use dashboard::timeseries::{by_time, Series};
+
+#[test]
+fn long_series_are_decimated() {
+    let mut series = Series::default();
+    for second in 0..100_000 {
+        series.push(second as f64, second as f64);
+    }
+
+    let points = series.points();
+    assert!(points.len() <= 4096);
+    assert!(points[0][0] < 100.0);
+    assert_eq!(points[points.len() - 1], [99_999.0, 99_999.0]);
+    // The first reading is kept as the heatmap baseline
+    assert_eq!(series.first(), Some([0.0, 0.0]));
+}
+
+#[test]
+fn points_are_matched_by_time() {
+    // A well without frames in a sampling window has no reading then
+    let rows = by_time([("A1", vec![[0.0, 1.0], [1.0, 2.0]]), ("A2", vec![[1.0, 4.0]])]);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].1.get("A2"), None);
+    assert_eq!(rows[1].1.get("A2"), Some(&4.0));
+}
@@ -14,11 +14,14 @@
 #define LTR303_ALS_MEAS_RATE_REG     0x85
 #define LTR303_ALS_DATA_CH1_REG      0x88
 
-#define ALS_GAIN  96.0
-#define ALS_INT    1.0
+// Set in the first byte of a frame carrying the raw channels of a sensor
+#define RAW_FRAME  0b01000000
+// Bits of the first byte of a raw frame holding the gain the channels were measured with
+#define GAIN_BITS  0b00111000
+// Followed by the sensor, its ALS_CONTR and its ALS_MEAS_RATE
+#define CONFIGURE_SENSOR  0b11111110
 
 uint8_t current_channel = 0;
-uint16_t sensor_data = 0;
 
 // Initialize a 64-bit array to keep track of the state of each bit
 uint8_t bitArray[8] = {0, 0, 0, 0, 0, 0, 0, 0};
@@ -63,29 +66,11 @@ void loop(){
 
     // FROM MUX 1
     set_channel(MUX_ADDR_1, current_channel);
-    
-    sensor_data = (uint16_t) read_sensor();
-    
-    uint8_t low_byte = sensor_data & 0xFF;
-    uint8_t high_byte = (sensor_data >> 8) & 0xFF;
-
-    Serial.write(i);
-    Serial.write(low_byte);
-    Serial.write(high_byte);
-    
+    send_sensor(RAW_FRAME | i);
     set_channel(MUX_ADDR_1, 0);
    
     set_channel(MUX_ADDR_2, current_channel);
-    
-    sensor_data = (uint16_t) read_sensor();
-    
-    low_byte = sensor_data & 0xFF;
-    high_byte = (sensor_data >> 8) & 0xFF;
-
-    Serial.write(0b10000000 | i);
-    Serial.write(low_byte);
-    Serial.write(high_byte);
-    
+    send_sensor(0b10000000 | RAW_FRAME | i);
     set_channel(MUX_ADDR_2, 0);
   }
   
@@ -97,6 +82,11 @@ void loop(){
       turn_leds_off();
       cmd = Serial.read();
       analogWrite(ENABLE, cmd);
+    } else if(cmd == CONFIGURE_SENSOR) {
+      uint8_t args[3];
+      if(Serial.readBytes(args, 3) == 3) {
+        configure_sensor(args[0], args[1], args[2]);
+      }
     } else {
       int state = (cmd & 0b10000000) ? HIGH : LOW;
       int bitIndex = cmd & 0b01111111;
@@ -168,47 +158,64 @@ void set_channel(uint8_t addr, uint8_t channel) {
 }
 
 void initialize_sensor() {
-  // This will set the gain of the sensor to x96
+  // This will set the gain of the sensor to x96, the integration rate to 50ms and measurement
+  // rate to 100ms until the dashboard configures it
+  write_settings(0x1D, 0x09);
+}
+
+// The sensor is given like in the first byte of a frame: the mux in the highest bit and the
+// channel in the lowest three
+void configure_sensor(uint8_t sensor, uint8_t contr, uint8_t meas_rate) {
+  uint8_t addr = (sensor & 0b10000000) ? MUX_ADDR_2 : MUX_ADDR_1;
+
+  set_channel(addr, 1 << (sensor & 0b00000111));
+  write_settings(contr, meas_rate);
+  set_channel(addr, 0);
+}
+
+void write_settings(uint8_t contr, uint8_t meas_rate) {
   Wire.beginTransmission(LTR303_ADDR);
   Wire.write(LTR303_ALS_CONTR_REG);
-  Wire.write(0x1D);
+  Wire.write(contr);
   Wire.endTransmission();
 
-  // This will set the integration rate to 50ms and measurement rate to 100ms
   Wire.beginTransmission(LTR303_ADDR);
   Wire.write(LTR303_ALS_MEAS_RATE_REG);
-  Wire.write(0x09);
+  Wire.write(meas_rate);
   Wire.endTransmission();
 }
 
-float read_sensor() {
+// Sends the raw channels of the selected sensor, the lux is computed by the dashboard
+void send_sensor(uint8_t header) {
+  uint16_t ch0 = 0;
+  uint16_t ch1 = 0;
+  uint8_t status = 0;
+  read_sensor(&ch0, &ch1, &status);
+
+  // The gain of the data sits in bits 6:4 of ALS_STATUS
+  Serial.write(header | ((status >> 1) & GAIN_BITS));
+  Serial.write(ch0 & 0xFF);
+  Serial.write((ch0 >> 8) & 0xFF);
+  Serial.write(ch1 & 0xFF);
+  Serial.write((ch1 >> 8) & 0xFF);
+}
+
+void read_sensor(uint16_t *ch0, uint16_t *ch1, uint8_t *status) {
   // Set to register to data
   Wire.beginTransmission(LTR303_ADDR);
   Wire.write(LTR303_ALS_DATA_CH1_REG);
   Wire.endTransmission();
 
-  // Read 4 bytes and wait until you have 4 bytes to read
-  Wire.requestFrom(LTR303_ADDR, 4);
-  while(Wire.available() != 4);
-
-  // Read and print channel 1 and 0
-  uint16_t reading_1 = Wire.read();
-  reading_1 |= Wire.read() << 8;
-
-  uint16_t reading_0 = Wire.read();
-  reading_0 |= Wire.read() << 8;
-
-  float ch0 = (float) reading_0;
-  float ch1 = (float) reading_1;
- 
-  float ratio = ch1/(ch0+ch1);
-  if (ratio < 0.45) {
-    return (1.7743*ch0 + 1.1059*ch1)/ALS_GAIN/ALS_INT;
-  } else if (ratio < 0.64 && ratio >= 0.45) {
-    return (4.2785*ch0 - 1.9548*ch1)/ALS_GAIN/ALS_INT;
-  } else if (ratio < 0.85 && ratio >= 0.64) {
-    return (0.5926*ch0 + 0.1185*ch1)/ALS_GAIN/ALS_INT;
-  } else {
-    return 0.0;
-  }
+  // Read the channels and ALS_STATUS following them, waiting until the 5 bytes are there
+  Wire.requestFrom(LTR303_ADDR, 5);
+  while(Wire.available() != 5);
+
+  // Read channel 1 and 0
+  *ch1 = Wire.read();
+  *ch1 |= Wire.read() << 8;
+
+  *ch0 = Wire.read();
+  *ch0 |= Wire.read() << 8;
+
+  *status = Wire.read();
 }